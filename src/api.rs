@@ -0,0 +1,519 @@
+use anyhow::{Context, Result};
+use log::{debug, error, warn};
+use reqwest::{header::HeaderMap, Client, ClientBuilder, StatusCode};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::Config,
+    hypixel::body_snippet,
+    metrics::{metrics, Api},
+    Uuid,
+};
+
+pub const HYPIXEL_API: &str = "https://api.hypixel.net";
+
+/// Mojang answers name lookups quickly, so a slow one is better retried than waited on.
+const MOJANG_TIMEOUT: Duration = Duration::from_secs(15);
+/// Some Hypixel endpoints fail in confusing ways for requests without a user agent.
+const USER_AGENT: &str = concat!("bwoverlay-rs/", env!("CARGO_PKG_VERSION"));
+
+/// Below this many remaining requests a warning is logged, before Hypixel starts answering 429.
+const LOW_QUOTA_WARNING: i32 = 10;
+
+#[derive(Debug)]
+pub enum HypixelApiError {
+    /// Hypixel refused the key with 401 or 403.
+    KeyRejected(StatusCode),
+    /// The request could not be sent or its body read. The URL, and with it the API key, is
+    /// stripped from the error.
+    Request(reqwest::Error),
+}
+
+impl fmt::Display for HypixelApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HypixelApiError::KeyRejected(status) => {
+                write!(f, "Hypixel rejected the API key: {status}")
+            }
+            HypixelApiError::Request(_) => write!(f, "Request to Hypixel failed"),
+        }
+    }
+}
+
+impl std::error::Error for HypixelApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HypixelApiError::KeyRejected(_) => None,
+            HypixelApiError::Request(e) => Some(e),
+        }
+    }
+}
+
+/// reqwest errors embed the request URL, which contains the API key.
+impl From<reqwest::Error> for HypixelApiError {
+    fn from(e: reqwest::Error) -> Self {
+        HypixelApiError::Request(e.without_url())
+    }
+}
+
+/// Everything that can go wrong between a name in `/who` and that player's stats. Converts into
+/// `anyhow::Error` like any other error, so callers can keep using `?` and downcast when they care
+/// which step failed.
+#[derive(Debug)]
+pub enum PlayerLookupError {
+    /// Mojang answered a UUID lookup with a non-success status.
+    MojangApiError {
+        status: u16,
+        body: String,
+    },
+    /// A Hypixel endpoint answered with a non-success status other than 429.
+    HypixelApiError {
+        status: u16,
+        body: String,
+    },
+    /// A response body wasn't the JSON expected. `context` says which response.
+    ParseError {
+        source: serde_json::Error,
+        context: String,
+    },
+    UuidParseError {
+        source: uuid::Error,
+        raw: String,
+    },
+    /// Hypixel answered 429. `retry_after` is the seconds until the quota resets, if it said.
+    RateLimited {
+        retry_after: Option<u32>,
+    },
+    /// Hypixel answered with `"player": null`, the player has never joined the server.
+    PlayerNotFound {
+        uuid_or_name: String,
+    },
+}
+
+impl fmt::Display for PlayerLookupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayerLookupError::MojangApiError { status, body } => {
+                write!(f, "Mojang API returned {status}: {body}")
+            }
+            PlayerLookupError::HypixelApiError { status, body } => {
+                write!(f, "Hypixel API returned {status}: {body}")
+            }
+            PlayerLookupError::ParseError { source, context } => {
+                write!(f, "Failed to parse {context}: {source}")
+            }
+            PlayerLookupError::UuidParseError { raw, .. } => {
+                write!(f, "Invalid UUID format: {raw}")
+            }
+            PlayerLookupError::RateLimited {
+                retry_after: Some(seconds),
+            } => write!(f, "Hypixel rate limit reached, retry in {seconds}s"),
+            PlayerLookupError::RateLimited { retry_after: None } => {
+                write!(f, "Hypixel rate limit reached")
+            }
+            PlayerLookupError::PlayerNotFound { uuid_or_name } => {
+                write!(f, "Player {uuid_or_name} has never played on Hypixel")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PlayerLookupError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PlayerLookupError::ParseError { source, .. } => Some(source),
+            PlayerLookupError::UuidParseError { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Replaces every occurrence of `key` in `url` with a placeholder so it can be logged safely.
+/// Works on any string, so it is also used for response bodies and error messages.
+pub fn sanitize_url(url: &str, key: &str) -> String {
+    if key.is_empty() {
+        return url.to_string();
+    }
+
+    url.replace(key, "<API_KEY_REDACTED>")
+}
+
+/// A client builder with the configured timeouts, so no request can hang forever.
+fn client_builder(config: &Config) -> ClientBuilder {
+    Client::builder()
+        .timeout(Duration::from_secs(config.request_timeout_secs))
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .connection_verbose(cfg!(debug_assertions))
+}
+
+/// One HTTP client per API, each with its own connection pool, timeout and headers.
+pub struct ConnectionPool {
+    pub mojang: Arc<Client>,
+    pub hypixel: Arc<Client>,
+}
+
+impl ConnectionPool {
+    /// Mojang requests give up after `MOJANG_TIMEOUT`, Hypixel requests after
+    /// `request-timeout-secs` and send a `USER_AGENT`.
+    pub fn new(config: &Config) -> Result<Self> {
+        let mojang = client_builder(config)
+            .timeout(MOJANG_TIMEOUT)
+            .build()
+            .context("Failed to build the Mojang HTTP client")?;
+        let hypixel = client_builder(config)
+            .user_agent(USER_AGENT)
+            .build()
+            .context("Failed to build the Hypixel HTTP client")?;
+
+        Ok(ConnectionPool {
+            mojang: Arc::new(mojang),
+            hypixel: Arc::new(hypixel),
+        })
+    }
+
+    /// Sends every request through `client`, e.g. in tests against a `MockHypixelServer`.
+    #[cfg(test)]
+    pub fn shared(client: Client) -> Arc<Self> {
+        let client = Arc::new(client);
+        Arc::new(ConnectionPool {
+            mojang: Arc::clone(&client),
+            hypixel: client,
+        })
+    }
+}
+
+/// The API key's quota as reported by the `RateLimit-*` headers of a Hypixel response.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    pub remaining: i32,
+    /// Seconds until the quota is refilled.
+    pub reset: u64,
+}
+
+impl RateLimit {
+    /// `None` when either header is missing or not a number, like on Mojang responses.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        fn header<T: std::str::FromStr>(headers: &HeaderMap, name: &str) -> Option<T> {
+            headers.get(name)?.to_str().ok()?.trim().parse().ok()
+        }
+
+        Some(RateLimit {
+            remaining: header(headers, "RateLimit-Remaining")?,
+            reset: header(headers, "RateLimit-Reset")?,
+        })
+    }
+}
+
+/// Shared client for every Hypixel API endpoint, so all lookups reuse one connection pool.
+pub struct HypixelApiClient {
+    pool: Arc<ConnectionPool>,
+    api_key: String,
+    base_url: String,
+    /// Requests left in the current quota window as last reported by Hypixel, -1 until known.
+    quota_remaining: Arc<AtomicI32>,
+    /// When the current quota window ends.
+    quota_reset: Mutex<Option<Instant>>,
+}
+
+impl HypixelApiClient {
+    pub fn new(api_key: String, pool: Arc<ConnectionPool>) -> Self {
+        HypixelApiClient {
+            pool,
+            api_key,
+            base_url: HYPIXEL_API.to_string(),
+            quota_remaining: Arc::new(AtomicI32::new(-1)),
+            quota_reset: Mutex::new(None),
+        }
+    }
+
+    /// Sends requests to `base_url` instead of the real API, e.g. a `MockHypixelServer`.
+    #[cfg(test)]
+    pub fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    /// Requests left in the current quota window, -1 until Hypixel reported it.
+    #[cfg(test)]
+    pub fn quota_remaining(&self) -> i32 {
+        self.quota_remaining.load(Ordering::Relaxed)
+    }
+
+    /// The client for Mojang's API, which lookups resolve names with before asking Hypixel.
+    pub fn mojang(&self) -> &Client {
+        &self.pool.mojang
+    }
+
+    /// Requests `endpoint` (e.g. `player` or `v2/friends`) for `uuid` and returns the raw body.
+    pub async fn get(&self, endpoint: &str, uuid: &Uuid) -> Result<String> {
+        self.request(endpoint, Some(uuid)).await
+    }
+
+    /// Requests an `endpoint` that isn't about one player, e.g. `v2/boosters`.
+    pub async fn get_resource(&self, endpoint: &str) -> Result<String> {
+        self.request(endpoint, None).await
+    }
+
+    async fn request(&self, endpoint: &str, uuid: Option<&Uuid>) -> Result<String> {
+        let mut url = format!("{}/{endpoint}?key={}", self.base_url, self.api_key);
+        let mut target = String::new();
+        if let Some(uuid) = uuid {
+            url.push_str(&format!("&uuid={uuid}"));
+            target = format!(" for {uuid}");
+        }
+
+        self.wait_for_quota().await;
+        debug!("Requesting {}", sanitize_url(&url, &self.api_key));
+        metrics().hypixel_call();
+
+        let started = Instant::now();
+        let response = self
+            .pool
+            .hypixel
+            .get(&url)
+            .send()
+            .await
+            .inspect_err(|_| metrics().api_error())
+            .map_err(HypixelApiError::from)
+            .with_context(|| format!("Failed to request Hypixel {endpoint}{target}"))?;
+        metrics().request_completed(Api::Hypixel, response.remote_addr(), started.elapsed());
+
+        let rate_limit = RateLimit::from_headers(response.headers());
+        if let Some(rate_limit) = &rate_limit {
+            self.record_quota(rate_limit);
+        }
+
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(HypixelApiError::from)
+            .with_context(|| format!("Failed to read Hypixel {endpoint} response{target}"))?;
+
+        if !status.is_success() {
+            metrics().api_error();
+            let body = sanitize_url(&body, &self.api_key);
+            error!("Hypixel {endpoint} returned an error: {body}");
+            if status == StatusCode::TOO_MANY_REQUESTS {
+                return Err(PlayerLookupError::RateLimited {
+                    retry_after: rate_limit.map(|rate_limit| rate_limit.reset as u32),
+                }
+                .into());
+            }
+            return Err(PlayerLookupError::HypixelApiError {
+                status: status.as_u16(),
+                body: body_snippet(&body),
+            }
+            .into());
+        }
+
+        Ok(body)
+    }
+
+    fn record_quota(&self, rate_limit: &RateLimit) {
+        let RateLimit { remaining, reset } = *rate_limit;
+        debug!("Hypixel quota: {remaining} requests remaining, resets in {reset}s");
+        if remaining < LOW_QUOTA_WARNING {
+            warn!("Hypixel quota is almost used up: {remaining} requests remaining, resets in {reset}s");
+        }
+
+        self.quota_remaining.store(remaining, Ordering::Relaxed);
+        *self.quota_reset.lock().unwrap() = Some(Instant::now() + Duration::from_secs(reset));
+    }
+
+    /// Holds a request back until the quota window resets once Hypixel reports it as used up,
+    /// instead of sending it just to get a 429.
+    async fn wait_for_quota(&self) {
+        if self.quota_remaining.load(Ordering::Relaxed) > 0 {
+            return;
+        }
+        let Some(reset) = *self.quota_reset.lock().unwrap() else {
+            return;
+        };
+
+        if reset > Instant::now() {
+            debug!(
+                "Hypixel quota used up, waiting {}s for it to reset",
+                (reset - Instant::now()).as_secs()
+            );
+            tokio::time::sleep_until(reset.into()).await;
+        }
+    }
+
+    /// Confirms the API key is accepted by Hypixel. The `/key` endpoint is gone, so this asks for
+    /// the boosters, one of the cheapest requests that needs a key.
+    pub async fn check_key(&self) -> Result<()> {
+        let response = self
+            .pool
+            .hypixel
+            .get(format!(
+                "{}/v2/boosters?key={}",
+                self.base_url, self.api_key
+            ))
+            .send()
+            .await
+            .map_err(HypixelApiError::from)
+            .context("Failed to reach the Hypixel API")?;
+
+        match response.status() {
+            status @ (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
+                Err(HypixelApiError::KeyRejected(status).into())
+            }
+            // A throttled key was still recognized
+            status if status.is_success() || status == StatusCode::TOO_MANY_REQUESTS => Ok(()),
+            status => anyhow::bail!("Hypixel answered {status}, the API key could not be checked"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::MockHypixelServer;
+    use std::time::Instant;
+    use tokio::net::TcpListener;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|&(name, value)| (name.parse().unwrap(), value.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_rate_limit_headers() {
+        let response = headers(&[
+            ("content-type", "application/json"),
+            ("ratelimit-limit", "300"),
+            ("ratelimit-remaining", "297"),
+            ("ratelimit-reset", "42"),
+        ]);
+        assert_eq!(
+            RateLimit::from_headers(&response),
+            Some(RateLimit {
+                remaining: 297,
+                reset: 42
+            })
+        );
+
+        let mojang = headers(&[("content-type", "application/json")]);
+        assert_eq!(RateLimit::from_headers(&mojang), None);
+
+        let garbled = headers(&[("ratelimit-remaining", "lots"), ("ratelimit-reset", "42")]);
+        assert_eq!(RateLimit::from_headers(&garbled), None);
+    }
+
+    #[tokio::test]
+    async fn rate_limited_requests_fail_and_record_the_quota() {
+        let server = MockHypixelServer::start().await;
+        server.register_rate_limit();
+        let client =
+            HypixelApiClient::new("key".to_string(), ConnectionPool::shared(Client::new()))
+                .with_base_url(server.base_url());
+
+        let err = client
+            .get("player", &"unknown".to_string())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<PlayerLookupError>(),
+            Some(PlayerLookupError::RateLimited {
+                retry_after: Some(60)
+            })
+        ));
+        assert_eq!(client.quota_remaining.load(Ordering::Relaxed), 299);
+    }
+
+    #[test]
+    fn sanitize_url_redacts_every_occurrence_of_the_key() {
+        let key = "0b8f2c7e-1d4a-4f7b-9c2e-3a5d6f8e9b10";
+        let url = format!("https://api.hypixel.net/v2/player?key={key}&uuid=x (key {key})");
+
+        assert_eq!(
+            sanitize_url(&url, key),
+            "https://api.hypixel.net/v2/player?key=<API_KEY_REDACTED>&uuid=x \
+             (key <API_KEY_REDACTED>)"
+        );
+        assert_eq!(sanitize_url("no key set", ""), "no key set");
+    }
+
+    #[tokio::test]
+    async fn failed_requests_never_show_the_key() {
+        // Nothing listens on this port once the listener is dropped
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let key = "0b8f2c7e-1d4a-4f7b-9c2e-3a5d6f8e9b10";
+        let client = HypixelApiClient::new(key.to_string(), ConnectionPool::shared(Client::new()))
+            .with_base_url(format!("http://{addr}"));
+
+        let err = client.get("player", &"uuid".to_string()).await.unwrap_err();
+
+        for output in [format!("{err}"), format!("{err:#}"), format!("{err:?}")] {
+            assert!(!output.contains(key), "{output}");
+        }
+    }
+
+    #[tokio::test]
+    async fn checks_the_key_against_the_boosters() {
+        let server = MockHypixelServer::start().await;
+        let client =
+            HypixelApiClient::new("key".to_string(), ConnectionPool::shared(Client::new()))
+                .with_base_url(server.base_url());
+        client.check_key().await.unwrap();
+
+        server.register_invalid_key();
+        let err = client.check_key().await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<HypixelApiError>(),
+            Some(HypixelApiError::KeyRejected(StatusCode::FORBIDDEN))
+        ));
+    }
+
+    #[tokio::test]
+    async fn requests_time_out() {
+        // Accepts connections but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
+            }
+        });
+
+        let config = Config {
+            request_timeout_secs: 1,
+            connect_timeout_secs: 1,
+            ..Config::default()
+        };
+        let client = HypixelApiClient::new(
+            "key".to_string(),
+            Arc::new(ConnectionPool::new(&config).unwrap()),
+        )
+        .with_base_url(format!("http://{addr}"));
+
+        let started = Instant::now();
+        let err = client.get_resource("v2/boosters").await.unwrap_err();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        // The reqwest error survives as a source, so telemetry can tell timeouts apart
+        assert!(err
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<reqwest::Error>())
+            .is_some_and(reqwest::Error::is_timeout));
+        assert_eq!(
+            crate::telemetry::sanitize_error(&err)["error_type"],
+            "http_timeout"
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use serde_derive::Deserialize;
+
+/// Arcade subgames, which Hypixel lists directly under `player.stats` next to `Bedwars`. Flattened
+/// into `ApiStats`, so supporting another subgame only needs a field here and a line in
+/// `totals`.
+#[derive(Deserialize, Clone, Default)]
+pub struct ApiArcadeStats {
+    #[serde(rename = "Quake")]
+    pub quakecraft: Option<ApiQuakecraftStats>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct ApiQuakecraftStats {
+    pub kills: Option<i32>,
+    pub deaths: Option<i32>,
+    pub wins: Option<i32>,
+}
+
+impl ApiArcadeStats {
+    /// Adds up every subgame the player has stats for.
+    pub fn totals(&self) -> ArcadeStats {
+        let mut totals = ArcadeStats::default();
+        if let Some(quake) = &self.quakecraft {
+            totals.add(quake.kills, quake.deaths, quake.wins);
+        }
+        totals
+    }
+}
+
+/// A player's Arcade stats summed over every supported subgame. All zero when they never played
+/// one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArcadeStats {
+    pub kills: i32,
+    pub deaths: i32,
+    pub wins: i32,
+}
+
+impl ArcadeStats {
+    fn add(&mut self, kills: Option<i32>, deaths: Option<i32>, wins: Option<i32>) {
+        self.kills += kills.unwrap_or(0);
+        self.deaths += deaths.unwrap_or(0);
+        self.wins += wins.unwrap_or(0);
+    }
+
+    /// Kills per death, `None` when the player has no Arcade kills or deaths at all.
+    pub fn kdr(&self) -> Option<f32> {
+        (self.kills > 0 || self.deaths > 0).then(|| self.kills as f32 / self.deaths.max(1) as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quakecraft_counts_towards_arcade_totals() {
+        let stats: ApiArcadeStats = serde_json::from_str(
+            r#"{"Quake": {"kills": 300, "deaths": 120, "wins": 4}, "Bedwars": {}}"#,
+        )
+        .unwrap();
+        let totals = stats.totals();
+
+        assert_eq!(
+            totals,
+            ArcadeStats {
+                kills: 300,
+                deaths: 120,
+                wins: 4
+            }
+        );
+        assert_eq!(totals.kdr(), Some(2.5));
+        assert_eq!(ApiArcadeStats::default().totals().kdr(), None);
+    }
+}
@@ -0,0 +1,90 @@
+use anyhow::{Context, Result};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    api::{ConnectionPool, HypixelApiClient},
+    config::Config,
+    get_hypixel_data, get_player_uuids,
+};
+
+const BENCHMARK_PLAYER: &str = "Notch";
+const BENCHMARK_ROUNDS: usize = 10;
+
+/// min/mean/max/p95 of one endpoint's round trips.
+struct LatencyStats {
+    min: Duration,
+    mean: Duration,
+    max: Duration,
+    p95: Duration,
+}
+
+impl LatencyStats {
+    fn from_samples(samples: &mut [Duration]) -> Self {
+        samples.sort();
+        let total: Duration = samples.iter().sum();
+        // Nearest-rank percentile
+        let p95_rank = (samples.len() * 95).div_ceil(100).max(1);
+
+        LatencyStats {
+            min: samples[0],
+            mean: total / samples.len() as u32,
+            max: samples[samples.len() - 1],
+            p95: samples[p95_rank - 1],
+        }
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Looks up `BENCHMARK_PLAYER` through the same Mojang and Hypixel paths the overlay uses,
+/// `BENCHMARK_ROUNDS` times, and prints the latency of each endpoint.
+pub async fn run(config: &Config) -> Result<()> {
+    let client = HypixelApiClient::new(
+        config.api_key.clone(),
+        Arc::new(ConnectionPool::new(config)?),
+    );
+    let mut mojang = Vec::with_capacity(BENCHMARK_ROUNDS);
+    let mut hypixel = Vec::with_capacity(BENCHMARK_ROUNDS);
+
+    for round in 1..=BENCHMARK_ROUNDS {
+        println!("Round {round}/{BENCHMARK_ROUNDS}");
+
+        let started = Instant::now();
+        let (players, _) =
+            get_player_uuids(client.mojang(), vec![BENCHMARK_PLAYER.to_string()]).await?;
+        mojang.push(started.elapsed());
+        let uuid = players
+            .into_keys()
+            .next()
+            .with_context(|| format!("Could not resolve the UUID of {BENCHMARK_PLAYER}"))?;
+
+        let started = Instant::now();
+        get_hypixel_data(uuid, &client)
+            .await
+            .with_context(|| format!("Failed to fetch Hypixel stats for {BENCHMARK_PLAYER}"))?;
+        hypixel.push(started.elapsed());
+    }
+
+    println!(
+        "\n{:<10}{:>10}{:>10}{:>10}{:>10}",
+        "ENDPOINT", "MIN", "MEAN", "MAX", "P95"
+    );
+    for (name, samples) in [("Mojang", &mut mojang), ("Hypixel", &mut hypixel)] {
+        let stats = LatencyStats::from_samples(samples);
+        println!(
+            "{:<10}{:>10}{:>10}{:>10}{:>10}",
+            name,
+            millis(stats.min),
+            millis(stats.mean),
+            millis(stats.max),
+            millis(stats.p95),
+        );
+    }
+
+    Ok(())
+}
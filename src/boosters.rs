@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use log::{error, info};
+use serde_derive::Deserialize;
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use crate::{api::HypixelApiClient, comparable_uuid, Uuid};
+
+/// Boosters last an hour or more, so the list is refreshed about as often as one runs out.
+const BOOSTERS_TTL: Duration = Duration::from_secs(60 * 60);
+/// How long to wait after a failed fetch, so an outage doesn't hold up every lobby.
+const RETRY_AFTER_FAILURE: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Deserialize)]
+struct ApiBoosters {
+    boosters: Vec<ApiBooster>,
+}
+
+/// One queued or active network booster.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiBooster {
+    purchaser_uuid: Uuid,
+    /// The coin multiplier, e.g. 3 for a triple coins booster.
+    #[serde(default)]
+    amount: f32,
+    /// How long the booster runs, in seconds.
+    #[serde(default)]
+    original_length: i32,
+}
+
+pub async fn get_booster_purchasers(client: &HypixelApiClient) -> Result<Vec<Uuid>> {
+    let body = client.get_resource("v2/boosters").await?;
+    let boosters: ApiBoosters =
+        serde_json::from_str(&body).context("Failed to parse the booster list")?;
+
+    let queued_secs: i32 = boosters
+        .boosters
+        .iter()
+        .map(|booster| booster.original_length)
+        .sum();
+    let best_amount = boosters
+        .boosters
+        .iter()
+        .map(|booster| booster.amount)
+        .fold(0.0, f32::max);
+    info!(
+        "Fetched {} boosters, {} minutes queued, up to {best_amount}x coins",
+        boosters.boosters.len(),
+        queued_secs / 60
+    );
+
+    Ok(boosters
+        .boosters
+        .into_iter()
+        .map(|booster| comparable_uuid(&booster.purchaser_uuid))
+        .collect())
+}
+
+/// Everyone with a network booster, refetched once `BOOSTERS_TTL` has passed.
+#[derive(Default)]
+pub struct BoosterList {
+    purchasers: HashSet<Uuid>,
+    /// When to fetch again, `None` until the first attempt.
+    next_fetch: Option<Instant>,
+}
+
+impl BoosterList {
+    /// Returns the cached purchasers, refetching them first if the cache is empty or stale.
+    /// A failed refresh keeps the previous list and is retried after `RETRY_AFTER_FAILURE`.
+    pub async fn get(&mut self, client: &HypixelApiClient) -> &HashSet<Uuid> {
+        let due = self
+            .next_fetch
+            .is_none_or(|next_fetch| Instant::now() >= next_fetch);
+
+        if due {
+            match get_booster_purchasers(client).await {
+                Ok(purchasers) => {
+                    info!("Loaded {} booster purchasers", purchasers.len());
+                    self.purchasers = purchasers.into_iter().collect();
+                    self.next_fetch = Some(Instant::now() + BOOSTERS_TTL);
+                }
+                Err(e) => {
+                    error!("Error while getting boosters: {e}");
+                    self.next_fetch = Some(Instant::now() + RETRY_AFTER_FAILURE);
+                }
+            }
+        }
+
+        &self.purchasers
+    }
+}
+
+pub fn has_active_booster(purchasers: &HashSet<Uuid>, uuid: &str) -> bool {
+    purchasers.contains(&comparable_uuid(uuid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{api::ConnectionPool, test_helpers::MockHypixelServer};
+    use reqwest::Client;
+
+    #[tokio::test]
+    async fn booster_purchasers_are_matched_and_cached() {
+        let server = MockHypixelServer::start().await;
+        server.register(
+            "v2/boosters",
+            "",
+            r#"{"success":true,"boosters":[
+                {"purchaserUuid":"069a79f444e94726a5befca90e38aaf5","amount":3.0,"originalLength":3600,"length":1200}
+            ]}"#,
+        );
+        let client =
+            HypixelApiClient::new("key".to_string(), ConnectionPool::shared(Client::new()))
+                .with_base_url(server.base_url());
+        let mut boosters = BoosterList::default();
+
+        let purchasers = boosters.get(&client).await.clone();
+        assert!(has_active_booster(
+            &purchasers,
+            "069A79F4-44E9-4726-A5BE-FCA90E38AAF5"
+        ));
+        assert!(!has_active_booster(
+            &purchasers,
+            "853c80ef3c3749fdaa49938b674adae6"
+        ));
+
+        boosters.get(&client).await;
+        assert_eq!(server.request_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn failed_fetches_are_not_retried_every_lobby() {
+        let server = MockHypixelServer::start().await;
+        server.register_invalid_key();
+        let client =
+            HypixelApiClient::new("key".to_string(), ConnectionPool::shared(Client::new()))
+                .with_base_url(server.base_url());
+        let mut boosters = BoosterList::default();
+
+        assert!(boosters.get(&client).await.is_empty());
+        assert!(boosters.get(&client).await.is_empty());
+        assert_eq!(server.request_count(), 1);
+    }
+}
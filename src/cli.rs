@@ -0,0 +1,116 @@
+use anyhow::Result;
+
+/// `bwoverlay profile ...` subcommands.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProfileCommand {
+    List,
+    Create(String),
+}
+
+/// `bwoverlay config ...` subcommands.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ConfigCommand {
+    /// Print a JSON Schema for `config.toml`, for editor autocomplete.
+    Schema,
+    /// Print the active config, defaults and profile included, as TOML.
+    Dump,
+}
+
+/// `bwoverlay compare <name_a> <name_b> [--markdown]`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CompareCommand {
+    pub name_a: String,
+    pub name_b: String,
+    /// Print a Markdown table instead of the terminal one.
+    pub markdown: bool,
+}
+
+/// Command line options. The overlay only takes a handful of flags, so they are parsed by hand.
+#[derive(Default)]
+pub struct Cli {
+    /// Check the config, API key and log path, then exit instead of starting the overlay.
+    pub validate_config: bool,
+    /// Measure Mojang and Hypixel API latency, then exit instead of starting the overlay.
+    pub benchmark_api: bool,
+    /// Look up every player from the `/who` output already in the log, then exit.
+    pub once: bool,
+    /// Profile from `profiles/<name>.toml` layered over `config.toml`.
+    pub profile: Option<String>,
+    pub profile_command: Option<ProfileCommand>,
+    pub config_command: Option<ConfigCommand>,
+    pub compare_command: Option<CompareCommand>,
+    /// Print what every stat abbreviation means, then exit.
+    pub glossary: bool,
+    /// Columns explained under every verbose table, from `--explain`. `all` explains every column.
+    /// Other log formats ignore it.
+    pub explain: Vec<String>,
+}
+
+impl Cli {
+    pub fn parse() -> Result<Self> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut cli = Cli::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--validate-config" => cli.validate_config = true,
+                "--benchmark-api" => cli.benchmark_api = true,
+                "--once" => cli.once = true,
+                "--profile" => {
+                    let Some(name) = args.next() else {
+                        anyhow::bail!("--profile needs a profile name");
+                    };
+                    cli.profile = Some(name);
+                }
+                "profile" => {
+                    cli.profile_command = Some(match args.next().as_deref() {
+                        Some("list") => ProfileCommand::List,
+                        Some("create") => {
+                            let Some(name) = args.next() else {
+                                anyhow::bail!("profile create needs a profile name");
+                            };
+                            ProfileCommand::Create(name)
+                        }
+                        _ => anyhow::bail!("Usage: bwoverlay profile <list|create NAME>"),
+                    });
+                }
+                "config" => {
+                    cli.config_command = Some(match args.next().as_deref() {
+                        Some("schema") => ConfigCommand::Schema,
+                        Some("dump") => ConfigCommand::Dump,
+                        _ => anyhow::bail!("Usage: bwoverlay config <schema|dump>"),
+                    });
+                }
+                "compare" => {
+                    let (Some(name_a), Some(name_b)) = (args.next(), args.next()) else {
+                        anyhow::bail!("Usage: bwoverlay compare <name_a> <name_b> [--markdown]");
+                    };
+                    cli.compare_command = Some(CompareCommand {
+                        name_a,
+                        name_b,
+                        markdown: false,
+                    });
+                }
+                "glossary" => cli.glossary = true,
+                "--explain" => {
+                    let Some(columns) = args.next() else {
+                        anyhow::bail!("--explain needs a column, e.g. --explain fkdr,bblr or all");
+                    };
+                    cli.explain
+                        .extend(columns.split(',').map(|column| column.trim().to_string()));
+                }
+                "--markdown" => match cli.compare_command.as_mut() {
+                    Some(compare) => compare.markdown = true,
+                    None => anyhow::bail!("--markdown only applies to compare"),
+                },
+                other => anyhow::bail!("Unknown argument: {other}"),
+            }
+        }
+
+        Ok(cli)
+    }
+}
@@ -0,0 +1,76 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+pub const RESET: &str = "\x1b[0m";
+pub const BOLD: &str = "\x1b[1m";
+pub const RED: &str = "\x1b[91m";
+pub const GREEN: &str = "\x1b[92m";
+pub const YELLOW: &str = "\x1b[93m";
+pub const AQUA: &str = "\x1b[96m";
+pub const GOLD: &str = "\x1b[33m";
+pub const GRAY: &str = "\x1b[37m";
+pub const WHITE: &str = "\x1b[97m";
+pub const BLUE: &str = "\x1b[94m";
+pub const LIGHT_PURPLE: &str = "\x1b[95m";
+pub const DARK_RED: &str = "\x1b[31m";
+pub const DARK_GREEN: &str = "\x1b[32m";
+pub const DARK_AQUA: &str = "\x1b[36m";
+pub const DARK_PURPLE: &str = "\x1b[35m";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turns coloring on or off for every `Colored` value formatted afterwards.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// A value that is wrapped in an ANSI color when formatted, unless coloring is disabled.
+pub struct Colored<T> {
+    value: T,
+    color: &'static str,
+}
+
+impl<T> Colored<T> {
+    pub fn new(value: T, color: &'static str) -> Self {
+        Colored { value, color }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Colored<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if enabled() {
+            write!(f, "{}{}{}", self.color, self.value, RESET)
+        } else {
+            write!(f, "{}", self.value)
+        }
+    }
+}
+
+pub fn rank_color(rank: &str) -> &'static str {
+    match rank {
+        "VIP" => GREEN,
+        "VIP+" => AQUA,
+        "MVP" => GOLD,
+        "MVP+" => AQUA,
+        "MVP++" => GOLD,
+        "ADMIN" | "OWNER" => RED,
+        "YOUTUBE" => YELLOW,
+        _ => GRAY,
+    }
+}
+
+pub fn fkdr_color(fkdr: f32) -> &'static str {
+    if fkdr > 5.0 {
+        RED
+    } else if fkdr > 2.0 {
+        YELLOW
+    } else {
+        GREEN
+    }
+}
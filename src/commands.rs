@@ -0,0 +1,88 @@
+use anyhow::Result;
+use log::{debug, error, warn};
+use std::{path::PathBuf, time::Duration};
+use tokio::{
+    sync::mpsc::{self, error::TrySendError},
+    time::MissedTickBehavior,
+};
+
+use crate::output::{self, OutputSink};
+
+/// Commands waiting beyond this are dropped rather than delivered long after they matter.
+const MAX_PENDING_COMMANDS: usize = 20;
+
+/// Sends chat commands to Minecraft through a named pipe, one every `delay_ms`, since the game
+/// drops commands that arrive together.
+pub struct CommandQueue {
+    sender: mpsc::Sender<String>,
+}
+
+impl CommandQueue {
+    /// Starts the drain loop writing to the FIFO at `fifo_path`.
+    pub async fn spawn(fifo_path: PathBuf, delay_ms: u64) -> Result<Self> {
+        let sink = output::from_config(&format!("fifo:{}", fifo_path.display())).await?;
+        let (sender, receiver) = mpsc::channel(MAX_PENDING_COMMANDS);
+        tokio::spawn(drain(receiver, sink, delay_ms));
+
+        Ok(CommandQueue { sender })
+    }
+
+    /// Queues `command`, or drops it with a warning when the queue is full.
+    pub fn push(&self, command: impl Into<String>) {
+        match self.sender.try_send(command.into()) {
+            Ok(()) => {}
+            Err(TrySendError::Full(command)) => {
+                warn!("Command queue is full, dropping {command:?}")
+            }
+            Err(TrySendError::Closed(command)) => {
+                warn!("Command queue has stopped, dropping {command:?}")
+            }
+        }
+    }
+}
+
+async fn drain(mut receiver: mpsc::Receiver<String>, sink: Box<dyn OutputSink>, delay_ms: u64) {
+    let mut interval = tokio::time::interval(Duration::from_millis(delay_ms.max(1)));
+    // Without this an idle queue would build up ticks and send the next commands back to back
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    while let Some(command) = receiver.recv().await {
+        interval.tick().await;
+        debug!("Sending command {command:?}");
+        if let Err(e) = sink.write(&command).await {
+            error!("Error while sending command: {e:#}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn commands_are_spaced_out() {
+        // A regular file stands in for the pipe
+        let path = std::env::temp_dir().join(format!(
+            "bwoverlay_commands_{}",
+            uuid::Uuid::new_v4().simple()
+        ));
+        std::fs::write(&path, "").unwrap();
+        let queue = CommandQueue::spawn(path.clone(), 100).await.unwrap();
+
+        let started = Instant::now();
+        queue.push("/who");
+        queue.push("/p list");
+        let written = loop {
+            let written = std::fs::read_to_string(&path).unwrap();
+            if written.lines().count() == 2 || started.elapsed() > Duration::from_secs(5) {
+                break written;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(written, "/who\n/p list\n");
+        assert!(started.elapsed() >= Duration::from_millis(100));
+    }
+}
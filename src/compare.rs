@@ -0,0 +1,217 @@
+use anyhow::{Context, Result};
+use std::sync::Arc;
+
+use crate::{
+    api::{ConnectionPool, HypixelApiClient},
+    color::{self, Colored},
+    config::Config,
+    display,
+    events::EventNormalizer,
+    get_hypixel_data, get_player_uuids,
+    hypixel::HypixelPlayer,
+};
+
+/// Which of the two compared players a stat favours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Player {
+    A,
+    B,
+}
+
+/// One row of a head-to-head comparison.
+#[derive(Debug)]
+pub struct StatComparison {
+    pub name: &'static str,
+    pub value_a: String,
+    pub value_b: String,
+    /// `None` on a tie or when either value is unknown.
+    pub winner: Option<Player>,
+}
+
+#[derive(Debug)]
+pub struct PlayerComparison {
+    pub stats: Vec<StatComparison>,
+}
+
+/// How the better value of each row is highlighted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComparisonFormat {
+    /// Bold ANSI when colors are on, for the terminal.
+    Ansi,
+    /// A GitHub Flavored Markdown table with `**bold**` winners.
+    Markdown,
+}
+
+/// Compares the stats where higher is better. A row with an unknown stat (`-1`, or a NaN ratio
+/// from zero deaths) has no winner. `Adj. level` is the level with double XP events taken out,
+/// for comparing accounts of different ages.
+pub fn compare_players(a: &HypixelPlayer, b: &HypixelPlayer) -> PlayerComparison {
+    let events = EventNormalizer::bundled();
+    let ratio = |name, a: f32, b: f32| stat(name, a, b, format!("{a:.2}"), format!("{b:.2}"));
+    let count = |name, a: i32, b: i32| stat(name, a as f32, b as f32, a.to_string(), b.to_string());
+
+    PlayerComparison {
+        stats: vec![
+            ratio("FKDR", a.fkdr, b.fkdr),
+            ratio("WLR", a.wlr, b.wlr),
+            count("Level", a.level, b.level),
+            count(
+                "Adj. level",
+                events.normalize(a).level,
+                events.normalize(b).level,
+            ),
+            stat(
+                "Index",
+                a.index_in(None),
+                b.index_in(None),
+                format!("{:.0}", a.index_in(None)),
+                format!("{:.0}", b.index_in(None)),
+            ),
+            count("Beds", a.bed_break, b.bed_break),
+            count("Finals", a.final_kills, b.final_kills),
+            count("Winstreak", a.winstreak, b.winstreak),
+        ],
+    }
+}
+
+fn stat(name: &'static str, a: f32, b: f32, value_a: String, value_b: String) -> StatComparison {
+    let known = |value: f32| value.is_finite() && value >= 0.0;
+    let winner = match (known(a), known(b)) {
+        (true, true) if a > b => Some(Player::A),
+        (true, true) if b > a => Some(Player::B),
+        _ => None,
+    };
+    let unknown = |value: String, is_known| if is_known { value } else { "-".to_string() };
+
+    StatComparison {
+        name,
+        value_a: unknown(value_a, known(a)),
+        value_b: unknown(value_b, known(b)),
+        winner,
+    }
+}
+
+impl PlayerComparison {
+    /// A `Stat | A | B` table with the better value of each row in bold.
+    pub fn format(&self, name_a: &str, name_b: &str, format: ComparisonFormat) -> String {
+        let bold = |value: &str, wins: bool| match (wins, format) {
+            (false, _) => value.to_string(),
+            (true, ComparisonFormat::Markdown) => format!("**{value}**"),
+            (true, ComparisonFormat::Ansi) => Colored::new(value, color::BOLD).to_string(),
+        };
+
+        let mut table = match format {
+            ComparisonFormat::Markdown => {
+                format!("| Stat | {name_a} | {name_b} |\n|---|--:|--:|\n")
+            }
+            ComparisonFormat::Ansi => format!("{:<10}{:>16}{:>16}\n", "Stat", name_a, name_b),
+        };
+        for stat in &self.stats {
+            let a = bold(&stat.value_a, stat.winner == Some(Player::A));
+            let b = bold(&stat.value_b, stat.winner == Some(Player::B));
+            match format {
+                ComparisonFormat::Markdown => {
+                    table.push_str(&format!("| {} | {a} | {b} |\n", stat.name));
+                }
+                ComparisonFormat::Ansi => {
+                    // Escape codes take no space on screen, so pad by the visible width
+                    let pad = |value: &str, visible: &str| {
+                        format!("{}{value}", " ".repeat(16 - visible.len().min(16)))
+                    };
+                    table.push_str(&format!(
+                        "{:<10}{}{}\n",
+                        stat.name,
+                        pad(&a, &stat.value_a),
+                        pad(&b, &stat.value_b)
+                    ));
+                }
+            }
+        }
+        table
+    }
+}
+
+/// `bwoverlay compare <name_a> <name_b>`: fetches both players and prints the comparison.
+pub async fn run(config: &Config, name_a: &str, name_b: &str, markdown: bool) -> Result<()> {
+    color::set_enabled(config.color_enabled());
+    display::set_rank_format(config.display.rank_format);
+    let client = HypixelApiClient::new(
+        config.api_key.clone(),
+        Arc::new(ConnectionPool::new(config)?),
+    );
+
+    let (uuids, _) = get_player_uuids(
+        client.mojang(),
+        vec![name_a.to_string(), name_b.to_string()],
+    )
+    .await?;
+    let fetch = |name: &str| {
+        let uuid = uuids
+            .iter()
+            .find(|(_, resolved)| resolved.eq_ignore_ascii_case(name))
+            .map(|(uuid, _)| uuid.clone());
+        let client = &client;
+        let name = name.to_string();
+        async move {
+            let uuid = uuid.with_context(|| format!("Could not resolve the UUID of {name}"))?;
+            get_hypixel_data(uuid, client).await
+        }
+    };
+    let (a, b) = tokio::try_join!(fetch(name_a), fetch(name_b))?;
+
+    let format = if markdown {
+        ComparisonFormat::Markdown
+    } else {
+        ComparisonFormat::Ansi
+    };
+    print!(
+        "{}",
+        compare_players(&a, &b).format(
+            &display::ranked_name(&a),
+            &display::ranked_name(&b),
+            format
+        )
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hypixel::HypixelPlayerBuilder;
+
+    #[test]
+    fn better_values_win_and_unknowns_never_do() {
+        let a = HypixelPlayerBuilder::new("Steve", "a")
+            .fkdr(4.0)
+            .level(300)
+            .winstreak(5)
+            .build();
+        let b = HypixelPlayerBuilder::new("Alex", "b")
+            .fkdr(2.5)
+            .level(450)
+            .winstreak(5)
+            .build();
+
+        let comparison = compare_players(&a, &b);
+        let winner = |name| {
+            comparison
+                .stats
+                .iter()
+                .find(|stat| stat.name == name)
+                .unwrap()
+                .winner
+        };
+
+        assert_eq!(winner("FKDR"), Some(Player::A));
+        assert_eq!(winner("Level"), Some(Player::B));
+        assert_eq!(winner("Winstreak"), None);
+        // Both players' final kills are unknown
+        assert_eq!(winner("Finals"), None);
+
+        let table = comparison.format("Steve", "Alex", ComparisonFormat::Markdown);
+        assert!(table.starts_with("| Stat | Steve | Alex |\n"));
+        assert!(table.contains("| FKDR | **4.00** | 2.50 |\n"));
+        assert!(table.contains("| Finals | - | - |\n"));
+    }
+}
@@ -0,0 +1,767 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    export::EXPORTERS, hypixel::BedwarsMode, i18n::LANGUAGES, normalizer::OPTIONAL_COLUMNS,
+    output::SinkSpec,
+};
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
+use tokio::{
+    fs::{self, File},
+    io::AsyncWriteExt,
+};
+
+pub const CONFIG_PATH: &str = "config.toml";
+
+/// Bumped whenever a config file needs migrating, with a matching entry in `MIGRATIONS`.
+pub const CURRENT_CONFIG_VERSION: u32 = 2;
+
+/// `MIGRATIONS[n]` upgrades a version `n + 1` config to version `n + 2`.
+const MIGRATIONS: [fn(&mut toml::Table); 1] = [migrate_v1_to_v2];
+
+/// Written above the generated default config to explain the less obvious keys.
+const DEFAULT_CONFIG_HEADER: &str = "\
+# request-timeout-secs: give up on a Hypixel request after this many seconds.
+#   Mojang requests always give up after 15 seconds.
+# connect-timeout-secs: give up on connecting after this many seconds, at most request-timeout-secs.
+
+";
+
+/// Unknown keys are rejected so typos like `api_key` don't silently fall back to the placeholder,
+/// while missing keys take their value from `Config::default()` so older config files keep working.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct Config {
+    /// Schema version of the file, configs from before versioning are version 1.
+    #[serde(rename = "config-version")]
+    pub config_version: u32,
+    #[serde(rename = "log-path")]
+    pub log_path: String,
+    /// More logs to watch alongside `log-path`, one per extra Minecraft instance. Output is
+    /// prefixed with `[I1]`, `[I2]`, ... in the order the logs are listed when this is set.
+    #[serde(rename = "extra-log-paths")]
+    pub extra_log_paths: Vec<String>,
+    #[serde(rename = "api-key")]
+    pub api_key: String,
+    #[serde(rename = "quit-level")]
+    pub quit_level: i32,
+    /// Color the output with ANSI escape codes. When unset, the output is colored if stdout is a
+    /// terminal, see `color_enabled`.
+    pub color: Option<bool>,
+    #[serde(rename = "log-format")]
+    pub log_format: LogFormat,
+    /// Where player stats go: `stdout`, `file:<path>` or `fifo:<path>`.
+    #[serde(rename = "output-sink")]
+    pub output_sink: String,
+    /// Language of the overlay's own output, like table headers and alerts. Log parsing always
+    /// expects English chat.
+    pub language: String,
+    #[serde(rename = "logger-format")]
+    pub logger_format: LoggerFormat,
+    /// Mode whose stats are shown until one is detected from chat.
+    #[serde(rename = "game-mode")]
+    pub game_mode: Option<BedwarsMode>,
+    #[serde(rename = "poll-interval-secs")]
+    pub poll_interval_secs: u64,
+    /// Named pipe Minecraft reads chat commands from, if command injection is set up. `/who` is
+    /// sent through it whenever a game starts.
+    #[serde(rename = "command-fifo")]
+    pub command_fifo: Option<String>,
+    /// Minimum gap between two commands written to `command-fifo`.
+    #[serde(rename = "command-delay-ms")]
+    pub command_delay_ms: u64,
+    /// How many players are looked up at once.
+    pub parallelism: usize,
+    #[serde(rename = "request-timeout-secs")]
+    pub request_timeout_secs: u64,
+    #[serde(rename = "connect-timeout-secs")]
+    pub connect_timeout_secs: u64,
+    /// How long cleanup may take after CTRL+C before the overlay exits anyway.
+    #[serde(rename = "shutdown-timeout-secs")]
+    pub shutdown_timeout_secs: u64,
+    /// A warning is logged when no `/who` response arrives this long after joining a lobby.
+    #[serde(rename = "who-timeout-secs")]
+    pub who_timeout_secs: u64,
+    #[serde(rename = "my-uuid")]
+    pub my_uuid: Option<String>,
+    /// Where anonymized error reports are posted. Telemetry is off unless this is set.
+    #[serde(rename = "telemetry-endpoint")]
+    pub telemetry_endpoint: Option<String>,
+    /// Chat line announcing the game is about to start, which re-prints the last lobby's stats.
+    #[serde(rename = "lobby-countdown-pattern")]
+    pub lobby_countdown_pattern: String,
+    /// Player whose stats the `norm_` columns are relative to.
+    #[serde(rename = "normalize-by-uuid")]
+    pub normalize_by_uuid: Option<String>,
+    /// Adds an `ArcadeKDR` column to the verbose table.
+    #[serde(rename = "display-arcade")]
+    pub display_arcade: bool,
+    /// Where each looked up lobby is saved: any of `json`, `csv` and `markdown`. Nothing is
+    /// saved by default.
+    pub exporters: Vec<String>,
+    /// Replace player names and UUIDs with pseudonyms in every export.
+    #[serde(rename = "redact-exports")]
+    pub redact_exports: bool,
+    /// The `[thresholds]` and `[display]` tables. Kept last since TOML tables must follow plain
+    /// keys.
+    pub thresholds: Thresholds,
+    pub display: DisplayConfig,
+}
+
+/// Options for the verbose table.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct DisplayConfig {
+    /// Optional columns to add, e.g. `norm_fkdr`.
+    pub columns: Vec<String>,
+    #[serde(rename = "rank-format")]
+    pub rank_format: RankFormat,
+    /// Players with less confidence in their stats than this have their ratios shown as `-?`.
+    #[serde(rename = "confidence-threshold")]
+    pub confidence_threshold: f32,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        DisplayConfig {
+            columns: Vec::new(),
+            rank_format: RankFormat::default(),
+            confidence_threshold: 0.3,
+        }
+    }
+}
+
+/// How ranks are shown in front of player names.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RankFormat {
+    /// `[MVP++]`.
+    #[default]
+    Full,
+    /// `M++`, for narrow terminals.
+    Short,
+    /// No rank at all.
+    None,
+}
+
+/// Limits that decide when a player is worth an alert.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct Thresholds {
+    /// High level players with less karma than this are flagged as possible smurfs.
+    #[serde(rename = "karma-alert-threshold")]
+    pub karma_alert_threshold: i32,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds {
+            karma_alert_threshold: 10_000,
+        }
+    }
+}
+
+/// How each looked up player is printed.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One `KEY:value` line per player as soon as their stats arrive.
+    Compact,
+    /// A table of the whole lobby once every player has been looked up.
+    Verbose,
+    /// The raw `HypixelPlayer` debug dump.
+    Debug,
+    /// One JSON object per player, for other programs to read.
+    Json,
+    /// A Markdown table of the whole lobby once every player has been looked up.
+    Markdown,
+}
+
+/// How the overlay's own log messages are written.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum LoggerFormat {
+    /// `simple_logger`'s human readable lines.
+    Text,
+    /// One JSON object per line, including structured fields like `uuid`, for log aggregators.
+    Json,
+}
+
+impl Config {
+    /// Whether to color the output, deciding from stdout when `color` is unset.
+    pub fn color_enabled(&self) -> bool {
+        self.color
+            .unwrap_or_else(|| std::io::stdout().is_terminal())
+    }
+}
+
+impl std::default::Default for Config {
+    fn default() -> Self {
+        let mut log_path = dirs::home_dir().unwrap();
+        #[cfg(target_os = "windows")]
+        {
+            log_path.push("AppData");
+            log_path.push("Roaming");
+        }
+        log_path.push(".minecraft");
+        log_path.push("logs");
+        log_path.push("latest.log");
+        if !log_path.exists() {
+            if let Some(discovered) = discover_log_paths().into_iter().next() {
+                log_path = discovered;
+            }
+        }
+
+        Config {
+            config_version: CURRENT_CONFIG_VERSION,
+            log_path: log_path.display().to_string(),
+            extra_log_paths: Vec::new(),
+            api_key: "INSERT_API_KEY_HERE".to_string(),
+            quit_level: 130,
+            color: None,
+            log_format: LogFormat::Compact,
+            output_sink: "stdout".to_string(),
+            language: "en".to_string(),
+            logger_format: LoggerFormat::Text,
+            game_mode: None,
+            poll_interval_secs: 2,
+            command_fifo: None,
+            command_delay_ms: 500,
+            parallelism: 4,
+            request_timeout_secs: 30,
+            connect_timeout_secs: 10,
+            shutdown_timeout_secs: 5,
+            who_timeout_secs: 10,
+            my_uuid: None,
+            telemetry_endpoint: None,
+            lobby_countdown_pattern: r"\[CHAT\] The game starts in \d+ seconds?!".to_string(),
+            normalize_by_uuid: None,
+            display_arcade: false,
+            exporters: Vec::new(),
+            redact_exports: false,
+            thresholds: Thresholds::default(),
+            display: DisplayConfig::default(),
+        }
+    }
+}
+
+/// Instance folders of third party launchers under the data directory, and where each instance
+/// keeps its game directory.
+const LAUNCHER_INSTANCE_DIRS: [(&str, &str); 5] = [
+    ("GDLauncher/instances", ".minecraft"),
+    // CurseForge instances are the game directory themselves
+    ("CurseForge/minecraft/Instances", ""),
+    ("FTB App/instances", ".minecraft"),
+    // Prism Launcher renamed `.minecraft` to `minecraft`, instances from before keep the old name
+    ("PrismLauncher/instances", "minecraft"),
+    ("PrismLauncher/instances", ".minecraft"),
+];
+
+/// Game directories of the official launcher under the macOS data directory,
+/// `~/Library/Application Support`. Newer launchers on Apple Silicon use the second one.
+#[cfg(target_os = "macos")]
+const MACOS_GAME_DIRS: [&str; 2] = ["minecraft", "com.mojang.minecraftlauncher"];
+
+/// Every `latest.log` found in a known launcher location.
+pub fn discover_log_paths() -> Vec<PathBuf> {
+    #[cfg(target_os = "windows")]
+    if let Some(data_dir) = dirs::data_dir() {
+        return launcher_log_paths(&data_dir);
+    }
+
+    #[cfg(target_os = "macos")]
+    if let Some(data_dir) = dirs::data_dir() {
+        let mut found = macos_log_paths(&data_dir);
+        found.extend(launcher_log_paths(&data_dir));
+        return found;
+    }
+
+    Vec::new()
+}
+
+/// The official launcher's logs in the macOS `data_dir` that exist.
+#[cfg(target_os = "macos")]
+fn macos_log_paths(data_dir: &Path) -> Vec<PathBuf> {
+    MACOS_GAME_DIRS
+        .iter()
+        .map(|game_dir| data_dir.join(game_dir).join("logs").join("latest.log"))
+        .filter(|log| log.is_file())
+        .collect()
+}
+
+/// Checks each instance one level below every launcher's instance folder in `data_dir`.
+#[cfg_attr(not(any(target_os = "windows", target_os = "macos")), allow(dead_code))]
+fn launcher_log_paths(data_dir: &Path) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+
+    for (instances, game_dir) in LAUNCHER_INSTANCE_DIRS {
+        let Ok(entries) = std::fs::read_dir(data_dir.join(instances)) else {
+            continue;
+        };
+        let mut logs: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path().join(game_dir).join("logs").join("latest.log"))
+            .filter(|log| log.is_file())
+            .collect();
+        logs.sort();
+        found.extend(logs);
+    }
+
+    found
+}
+
+/// Rejects API keys that can't be right: empty, the placeholder, or containing spaces.
+pub fn check_api_key(api_key: &str) -> Result<(), String> {
+    if api_key.is_empty() || api_key == Config::default().api_key {
+        Err("api-key has not been set".to_string())
+    } else if api_key.contains(char::is_whitespace) {
+        Err("api-key must not contain spaces".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns a description of every config value that is unusable, empty when all are fine.
+pub fn validate_config(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if let Err(problem) = check_api_key(&config.api_key) {
+        problems.push(problem);
+    }
+
+    if !config.log_path.ends_with("latest.log") {
+        problems.push(format!("log-path {} is not a latest.log", config.log_path));
+    }
+
+    if let Err(problem) = SinkSpec::parse(&config.output_sink) {
+        problems.push(problem);
+    }
+
+    if !LANGUAGES.contains(&config.language.as_str()) {
+        problems.push(format!(
+            "language {} is not supported, pick one of {}",
+            config.language,
+            LANGUAGES.join(", ")
+        ));
+    }
+
+    for exporter in &config.exporters {
+        if !EXPORTERS.contains(&exporter.as_str()) {
+            problems.push(format!(
+                "exporters has unknown exporter {exporter}, known exporters are {}",
+                EXPORTERS.join(", ")
+            ));
+        }
+    }
+
+    if let Some(my_uuid) = &config.my_uuid {
+        if uuid::Uuid::parse_str(my_uuid).is_err() {
+            problems.push(format!("my-uuid {my_uuid} is not a valid UUID"));
+        }
+    }
+
+    if let Some(uuid) = &config.normalize_by_uuid {
+        if uuid::Uuid::parse_str(uuid).is_err() {
+            problems.push(format!("normalize-by-uuid {uuid} is not a valid UUID"));
+        }
+    }
+
+    for column in &config.display.columns {
+        if !OPTIONAL_COLUMNS.contains(&column.as_str()) {
+            problems.push(format!(
+                "display.columns has unknown column {column}, known columns are {}",
+                OPTIONAL_COLUMNS.join(", ")
+            ));
+        } else if column.starts_with("norm_") && config.normalize_by_uuid.is_none() {
+            problems.push(format!(
+                "display.columns has {column}, which needs normalize-by-uuid to be set"
+            ));
+        }
+    }
+
+    if !(0.0..=1.0).contains(&config.display.confidence_threshold) {
+        problems.push(format!(
+            "display.confidence-threshold {} must be between 0 and 1",
+            config.display.confidence_threshold
+        ));
+    }
+
+    if let Err(e) = regex::Regex::new(&config.lobby_countdown_pattern) {
+        problems.push(format!("lobby-countdown-pattern is not a valid regex: {e}"));
+    }
+
+    if let Some(endpoint) = &config.telemetry_endpoint {
+        if !endpoint.starts_with("https://") {
+            problems.push(format!(
+                "telemetry-endpoint {endpoint} must be an https:// URL"
+            ));
+        }
+    }
+
+    problems
+}
+
+pub async fn read_config() -> Result<Config> {
+    let exists = matches!(fs::try_exists(CONFIG_PATH).await, Ok(true));
+
+    if !exists {
+        info!("Creating config file at {CONFIG_PATH}");
+        let mut f = File::create(CONFIG_PATH)
+            .await
+            .with_context(|| format!("Failed to create {CONFIG_PATH}"))?;
+
+        info!("Generating default config");
+        let config = new_config()?;
+        let config_str =
+            toml::to_string(&config).context("Failed to serialize the default config")?;
+        let _ = f
+            .write_all(format!("{DEFAULT_CONFIG_HEADER}{config_str}").as_bytes())
+            .await;
+    }
+
+    let config_str = std::fs::read_to_string(CONFIG_PATH)
+        .with_context(|| format!("Failed to read {CONFIG_PATH}"))?;
+    let (mut config, old_version) = parse_config(&config_str)?;
+
+    if old_version < CURRENT_CONFIG_VERSION {
+        let config_str = toml::to_string(&config).context("Failed to serialize the config")?;
+        fs::write(CONFIG_PATH, format!("{DEFAULT_CONFIG_HEADER}{config_str}"))
+            .await
+            .with_context(|| format!("Failed to write the migrated {CONFIG_PATH}"))?;
+        info!("Config migrated from v{old_version} to v{CURRENT_CONFIG_VERSION}");
+    }
+    normalize_config(&mut config);
+
+    Ok(config)
+}
+
+/// Parses the contents of `config.toml`, migrating it in memory, and returns the config along
+/// with the version the file was at.
+fn parse_config(config_str: &str) -> Result<(Config, u32)> {
+    let mut raw: toml::Table =
+        toml::from_str(config_str).with_context(|| format!("Failed to parse {CONFIG_PATH}"))?;
+    let old_version = migrate(&mut raw)?;
+    let config = raw
+        .try_into()
+        .with_context(|| format!("Failed to parse {CONFIG_PATH}"))?;
+
+    Ok((config, old_version))
+}
+
+/// The config written when none exists yet. With the `interactive` feature the API key and log
+/// path are asked for when run from a terminal, scripted runs keep the placeholder.
+#[cfg(feature = "interactive")]
+fn new_config() -> Result<Config> {
+    if std::io::stdin().is_terminal() {
+        crate::setup::prompt_config(Config::default())
+    } else {
+        Ok(Config::default())
+    }
+}
+
+#[cfg(not(feature = "interactive"))]
+fn new_config() -> Result<Config> {
+    Ok(Config::default())
+}
+
+/// Runs every migration the file is missing and returns the version it started at.
+fn migrate(raw: &mut toml::Table) -> Result<u32> {
+    let version = match raw.get("config-version") {
+        Some(version) => version
+            .as_integer()
+            .and_then(|version| u32::try_from(version).ok())
+            .context("config-version must be a positive number")?,
+        None => 1,
+    };
+    if version > CURRENT_CONFIG_VERSION {
+        anyhow::bail!(
+            "{CONFIG_PATH} is version {version}, newer than the supported {CURRENT_CONFIG_VERSION}"
+        );
+    }
+
+    for migration in &MIGRATIONS[(version.max(1) - 1) as usize..] {
+        migration(raw);
+    }
+    raw.insert(
+        "config-version".to_string(),
+        toml::Value::Integer(CURRENT_CONFIG_VERSION.into()),
+    );
+
+    Ok(version)
+}
+
+/// Version 2 introduced `config-version` itself. Fills in every key added since the first release
+/// with its default, so the upgraded file lists all of them.
+fn migrate_v1_to_v2(raw: &mut toml::Table) {
+    let Ok(defaults) = toml::Table::try_from(Config::default()) else {
+        return;
+    };
+
+    for (key, default) in defaults {
+        match (raw.get_mut(&key), default) {
+            (Some(toml::Value::Table(table)), toml::Value::Table(defaults)) => {
+                for (key, default) in defaults {
+                    table.entry(key).or_insert(default);
+                }
+            }
+            (Some(_), _) => {}
+            (None, default) => {
+                raw.insert(key, default);
+            }
+        }
+    }
+}
+
+/// Fixes up values that are wrong in an obvious way instead of rejecting the config.
+pub fn normalize_config(config: &mut Config) {
+    let mut log_path = PathBuf::from(&config.log_path);
+    if !log_path.ends_with("latest.log") {
+        warn!("Log path is not pointing to latest.log, pushing it to path");
+        log_path.push("latest.log");
+    }
+    config.log_path = log_path.to_string_lossy().to_string();
+
+    if config.connect_timeout_secs > config.request_timeout_secs {
+        warn!(
+            "connect-timeout-secs ({}) is longer than request-timeout-secs ({}), swapping them",
+            config.connect_timeout_secs, config.request_timeout_secs
+        );
+        std::mem::swap(
+            &mut config.connect_timeout_secs,
+            &mut config.request_timeout_secs,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_keys_need_to_be_set_and_without_spaces() {
+        assert!(check_api_key("0b8f2c7e-1d4a-4f7b-9c2e-3a5d6f8e9b10").is_ok());
+        assert!(check_api_key("").is_err());
+        assert!(check_api_key("INSERT_API_KEY_HERE").is_err());
+        assert!(check_api_key("my key").is_err());
+    }
+
+    #[test]
+    fn finds_launcher_instance_logs() {
+        let data_dir =
+            std::env::temp_dir().join(format!("bwoverlay-launchers-{}", std::process::id()));
+        let logs = [
+            "GDLauncher/instances/Bedwars/.minecraft/logs/latest.log",
+            "CurseForge/minecraft/Instances/Hypixel/logs/latest.log",
+            "FTB App/instances/Pvp/.minecraft/logs/latest.log",
+        ];
+        for log in logs {
+            let log = data_dir.join(log);
+            std::fs::create_dir_all(log.parent().unwrap()).unwrap();
+            std::fs::write(log, "").unwrap();
+        }
+        // An instance that was never launched has no log and is skipped
+        std::fs::create_dir_all(data_dir.join("GDLauncher/instances/Empty/.minecraft")).unwrap();
+
+        let found = launcher_log_paths(&data_dir);
+        std::fs::remove_dir_all(&data_dir).unwrap();
+
+        assert_eq!(found, logs.map(|log| data_dir.join(log)).to_vec(),);
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn finds_macos_launcher_logs() {
+        let data_dir = std::env::temp_dir().join(format!("bwoverlay-macos-{}", std::process::id()));
+        let logs = [
+            "com.mojang.minecraftlauncher/logs/latest.log",
+            "PrismLauncher/instances/Bedwars/minecraft/logs/latest.log",
+        ];
+        for log in logs {
+            let log = data_dir.join(log);
+            std::fs::create_dir_all(log.parent().unwrap()).unwrap();
+            std::fs::write(log, "").unwrap();
+        }
+        // The Intel launcher's folder without a log is skipped
+        std::fs::create_dir_all(data_dir.join("minecraft")).unwrap();
+
+        let mut found = macos_log_paths(&data_dir);
+        found.extend(launcher_log_paths(&data_dir));
+        std::fs::remove_dir_all(&data_dir).unwrap();
+
+        assert_eq!(found, logs.map(|log| data_dir.join(log)).to_vec());
+    }
+
+    fn round_trip(config: &Config) -> Config {
+        toml::from_str(&toml::to_string(config).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn config_round_trip() {
+        let config = Config::default();
+        assert_eq!(round_trip(&config), config);
+
+        // Every field set away from its default, so a field that doesn't survive TOML shows up
+        let config = Config {
+            config_version: CURRENT_CONFIG_VERSION,
+            log_path: "/tmp/latest.log".to_string(),
+            extra_log_paths: vec![
+                "/tmp/a/latest.log".to_string(),
+                "/tmp/b/latest.log".to_string(),
+            ],
+            api_key: "key".to_string(),
+            quit_level: 200,
+            color: Some(false),
+            log_format: LogFormat::Verbose,
+            output_sink: "file:stats.txt".to_string(),
+            language: "fr".to_string(),
+            logger_format: LoggerFormat::Json,
+            game_mode: Some(BedwarsMode::Fours),
+            poll_interval_secs: 7,
+            command_fifo: Some("/tmp/minecraft.fifo".to_string()),
+            command_delay_ms: 750,
+            parallelism: 9,
+            request_timeout_secs: 45,
+            connect_timeout_secs: 15,
+            shutdown_timeout_secs: 8,
+            who_timeout_secs: 20,
+            my_uuid: Some("069a79f4-44e9-4726-a5be-fca90e38aaf5".to_string()),
+            telemetry_endpoint: Some("https://example.com/report".to_string()),
+            lobby_countdown_pattern: r"\[CHAT\] Starting in \d+".to_string(),
+            normalize_by_uuid: Some("853c80ef3c3749fdaa49938b674adae6".to_string()),
+            display_arcade: true,
+            exporters: vec!["json".to_string(), "markdown".to_string()],
+            redact_exports: true,
+            thresholds: Thresholds {
+                karma_alert_threshold: 2_500,
+            },
+            display: DisplayConfig {
+                columns: vec!["norm_fkdr".to_string()],
+                rank_format: RankFormat::Short,
+                confidence_threshold: 0.15,
+            },
+        };
+        assert_eq!(round_trip(&config), config);
+    }
+
+    #[test]
+    fn migrates_v1_config() {
+        let mut raw: toml::Table = toml::from_str(
+            r#"
+            log-path = "/home/steve/.minecraft/logs/latest.log"
+            api-key = "key"
+            poll-interval-secs = 5
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(migrate(&mut raw).unwrap(), 1);
+        let config: Config = raw.try_into().unwrap();
+
+        assert_eq!(config.config_version, CURRENT_CONFIG_VERSION);
+        assert_eq!(config.api_key, "key");
+        assert_eq!(config.poll_interval_secs, 5);
+        assert_eq!(config.parallelism, Config::default().parallelism);
+    }
+
+    #[test]
+    fn rejects_configs_from_newer_versions() {
+        let mut raw: toml::Table = toml::from_str("config-version = 99").unwrap();
+
+        assert!(migrate(&mut raw).is_err());
+    }
+
+    #[test]
+    fn swaps_connect_timeout_longer_than_request_timeout() {
+        let mut config = Config {
+            request_timeout_secs: 5,
+            connect_timeout_secs: 20,
+            ..Config::default()
+        };
+
+        normalize_config(&mut config);
+
+        assert_eq!(config.request_timeout_secs, 20);
+        assert_eq!(config.connect_timeout_secs, 5);
+    }
+
+    #[test]
+    fn parse_errors_name_the_config_file() {
+        let err = parse_config("api-key = ").unwrap_err();
+        let message = format!("{err:#}");
+
+        assert!(
+            message.starts_with("Failed to parse config.toml: "),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn typos_are_rejected_by_name() {
+        let err = parse_config("api_key = \"0b8f2c7e\"").unwrap_err();
+        assert!(
+            format!("{err:#}").contains("unknown field `api_key`"),
+            "{err:#}"
+        );
+
+        // Nested tables reject unknown keys just the same
+        let err = parse_config("[display]\nrank_format = \"short\"").unwrap_err();
+        assert!(
+            format!("{err:#}").contains("unknown field `rank_format`"),
+            "{err:#}"
+        );
+
+        let err = parse_config("[thresholds]\nkarma = 5").unwrap_err();
+        assert!(
+            format!("{err:#}").contains("unknown field `karma`"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn configs_missing_newer_keys_still_load() {
+        let (config, version) = parse_config(
+            r#"
+            config-version = 2
+            api-key = "0b8f2c7e-1d4a-4f7b-9c2e-3a5d6f8e9b10"
+
+            [display]
+            rank-format = "short"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(version, 2);
+        assert_eq!(config.api_key, "0b8f2c7e-1d4a-4f7b-9c2e-3a5d6f8e9b10");
+        assert_eq!(config.display.rank_format, RankFormat::Short);
+        assert_eq!(
+            config.display.confidence_threshold,
+            DisplayConfig::default().confidence_threshold
+        );
+        assert_eq!(config.thresholds, Thresholds::default());
+        assert_eq!(config.log_path, Config::default().log_path);
+    }
+
+    #[test]
+    fn color_false_emits_no_ansi() {
+        let config: Config = toml::from_str("color = false").unwrap();
+        assert!(!config.color_enabled());
+
+        crate::color::set_enabled(config.color_enabled());
+        let player = crate::hypixel::HypixelPlayerBuilder::new("Steve", "uuid")
+            .rank("MVP+")
+            .level(412)
+            .fkdr(6.0)
+            .build();
+        let table = crate::display::format_player_verbose(&[player], None, None, false);
+
+        assert!(!table.contains('\x1b'));
+        // Left out of generated configs, so the terminal check runs on every start
+        assert!(!toml::to_string(&Config::default())
+            .unwrap()
+            .contains("color"));
+    }
+}
@@ -0,0 +1,287 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+
+use crate::{cli::ConfigCommand, config::Config, i18n::LANGUAGES};
+
+/// Every top level config key with its JSON Schema type and description. Kept by hand since the
+/// config is small, `schema_covers_every_config_key` catches keys missing here.
+const KEYS: &[(&str, &str, &str)] = &[
+    (
+        "config-version",
+        "integer",
+        "Schema version of the file, upgraded automatically on startup.",
+    ),
+    (
+        "log-path",
+        "string",
+        "Path to the Minecraft latest.log to watch.",
+    ),
+    (
+        "extra-log-paths",
+        "array",
+        "More latest.log files to watch, one per extra Minecraft instance.",
+    ),
+    ("api-key", "string", "Hypixel API key."),
+    (
+        "quit-level",
+        "integer",
+        "Exit code used when quitting with CTRL+C.",
+    ),
+    (
+        "color",
+        "boolean",
+        "Color the output with ANSI escape codes. When unset, colors are used if stdout is a terminal.",
+    ),
+    (
+        "log-format",
+        "string",
+        "How each looked up player is printed.",
+    ),
+    (
+        "output-sink",
+        "string",
+        "Where player stats go: stdout, file:<path> or fifo:<path>.",
+    ),
+    (
+        "language",
+        "string",
+        "Language of the overlay's own output, like table headers and alerts.",
+    ),
+    (
+        "logger-format",
+        "string",
+        "How the overlay's own log messages are written.",
+    ),
+    (
+        "game-mode",
+        "string",
+        "Mode whose stats are shown until one is detected from chat.",
+    ),
+    (
+        "poll-interval-secs",
+        "integer",
+        "How often the log is polled when file watching is unavailable.",
+    ),
+    (
+        "command-fifo",
+        "string",
+        "Named pipe Minecraft reads chat commands from. /who is sent through it when a game starts.",
+    ),
+    (
+        "command-delay-ms",
+        "integer",
+        "Minimum gap between two commands written to command-fifo.",
+    ),
+    (
+        "parallelism",
+        "integer",
+        "How many players are looked up at once.",
+    ),
+    (
+        "request-timeout-secs",
+        "integer",
+        "Give up on a Hypixel request after this many seconds. Mojang requests always give up after 15.",
+    ),
+    (
+        "connect-timeout-secs",
+        "integer",
+        "Give up on connecting after this many seconds, at most request-timeout-secs.",
+    ),
+    (
+        "shutdown-timeout-secs",
+        "integer",
+        "How long cleanup may take after CTRL+C before the overlay exits anyway.",
+    ),
+    (
+        "who-timeout-secs",
+        "integer",
+        "Warn when no /who response arrives this many seconds after joining a lobby.",
+    ),
+    (
+        "my-uuid",
+        "string",
+        "Your own UUID, used to tell you apart in the lobby.",
+    ),
+    (
+        "telemetry-endpoint",
+        "string",
+        "https:// URL anonymized error reports are posted to. Telemetry is off unless set.",
+    ),
+    (
+        "lobby-countdown-pattern",
+        "string",
+        "Regex for the chat line announcing the game is about to start.",
+    ),
+    (
+        "normalize-by-uuid",
+        "string",
+        "Player whose stats the norm_ columns are relative to.",
+    ),
+    (
+        "display-arcade",
+        "boolean",
+        "Add an ArcadeKDR column to the verbose table.",
+    ),
+    (
+        "exporters",
+        "array",
+        "Where each looked up lobby is saved: json, csv and markdown. Empty saves nothing.",
+    ),
+    (
+        "redact-exports",
+        "boolean",
+        "Replace player names and UUIDs with pseudonyms in every export.",
+    ),
+];
+
+const DISPLAY_KEYS: &[(&str, &str, &str)] = &[
+    (
+        "columns",
+        "array",
+        "Optional columns to add to the verbose table, e.g. norm_fkdr.",
+    ),
+    (
+        "rank-format",
+        "string",
+        "How ranks are shown in front of names: full, short or none.",
+    ),
+    (
+        "confidence-threshold",
+        "number",
+        "Players with less confidence in their stats than this, from 0 to 1, have their ratios shown as -?.",
+    ),
+];
+
+const THRESHOLD_KEYS: &[(&str, &str, &str)] = &[(
+    "karma-alert-threshold",
+    "integer",
+    "High level players with less karma than this are flagged as possible smurfs.",
+)];
+
+/// Values a string key is limited to.
+fn allowed_values(key: &str) -> Option<Value> {
+    match key {
+        "log-format" => Some(json!(["compact", "verbose", "debug", "json", "markdown"])),
+        "logger-format" => Some(json!(["text", "json"])),
+        "language" => Some(json!(LANGUAGES)),
+        "game-mode" => Some(json!(["solo", "doubles", "threes", "fours"])),
+        "rank-format" => Some(json!(["full", "short", "none"])),
+        _ => None,
+    }
+}
+
+fn properties(keys: &[(&str, &str, &str)], defaults: &Value) -> Map<String, Value> {
+    keys.iter()
+        .map(|&(key, kind, description)| {
+            let mut property = json!({ "type": kind, "description": description });
+            if kind == "array" {
+                property["items"] = json!({ "type": "string" });
+            }
+            if let Some(values) = allowed_values(key) {
+                property["enum"] = values;
+            }
+            // Optional keys have no default and are simply left out of the file
+            if let Some(default) = defaults.get(key).filter(|default| !default.is_null()) {
+                property["default"] = default.clone();
+            }
+            (key.to_string(), property)
+        })
+        .collect()
+}
+
+/// A JSON Schema draft-07 document for `config.toml`, with defaults taken from `Config::default`.
+pub fn schema() -> Result<Value> {
+    let defaults = serde_json::to_value(Config::default())
+        .context("Failed to serialize the default config")?;
+
+    let mut properties = properties(KEYS, &defaults);
+    properties.insert(
+        "thresholds".to_string(),
+        json!({
+            "type": "object",
+            "description": "Limits that decide when a player is worth an alert.",
+            "additionalProperties": false,
+            "properties": self::properties(THRESHOLD_KEYS, &defaults["thresholds"]),
+        }),
+    );
+
+    properties.insert(
+        "display".to_string(),
+        json!({
+            "type": "object",
+            "description": "Options for the verbose table.",
+            "additionalProperties": false,
+            "properties": self::properties(DISPLAY_KEYS, &defaults["display"]),
+        }),
+    );
+
+    Ok(json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "bwoverlay config",
+        "type": "object",
+        "additionalProperties": false,
+        "properties": properties,
+    }))
+}
+
+/// Prints the schema or the active config to stdout.
+pub fn run_command(command: ConfigCommand, config: &Config) -> Result<()> {
+    match command {
+        ConfigCommand::Schema => {
+            let schema = serde_json::to_string_pretty(&schema()?)
+                .context("Failed to serialize the config schema")?;
+            println!("{schema}");
+        }
+        ConfigCommand::Dump => print!("{}", dump(config)?),
+    }
+
+    Ok(())
+}
+
+/// The active config as TOML, with the API key replaced so the output is safe to paste into bug
+/// reports.
+fn dump(config: &Config) -> Result<String> {
+    let config = Config {
+        api_key: "<redacted>".to_string(),
+        ..config.clone()
+    };
+    toml::to_string(&config).context("Failed to serialize the config")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_covers_every_config_key() {
+        let schema = schema().unwrap();
+        let defaults = serde_json::to_value(Config::default()).unwrap();
+
+        for key in defaults.as_object().unwrap().keys() {
+            assert!(schema["properties"].get(key).is_some(), "{key} is missing");
+        }
+        for table in ["thresholds", "display"] {
+            for key in defaults[table].as_object().unwrap().keys() {
+                assert!(
+                    schema["properties"][table]["properties"].get(key).is_some(),
+                    "{table}.{key} is missing"
+                );
+            }
+        }
+        assert_eq!(schema["properties"]["command-delay-ms"]["default"], 500);
+        assert!(schema["properties"]["my-uuid"].get("default").is_none());
+    }
+
+    #[test]
+    fn dump_redacts_the_api_key() {
+        let config = Config {
+            api_key: "0123-secret-key".to_string(),
+            ..Config::default()
+        };
+
+        let dumped = dump(&config).unwrap();
+
+        assert!(!dumped.contains("0123-secret-key"));
+        assert!(dumped.contains(r#"api-key = "<redacted>""#));
+    }
+}
@@ -0,0 +1,372 @@
+use std::{fmt::Display, sync::RwLock};
+
+use crate::{
+    color::{self, Colored},
+    config::RankFormat,
+    hypixel::{BedwarsMode, HypixelPlayer, StarDisplay},
+    i18n::text,
+    normalizer::StatNormalizer,
+    team::PlayerGroup,
+};
+
+const NAME_WIDTH: usize = 32;
+const STAR_WIDTH: usize = 9;
+
+static RANK_FORMAT: RwLock<RankFormat> = RwLock::new(RankFormat::Full);
+static CONFIDENCE_THRESHOLD: RwLock<f32> = RwLock::new(0.3);
+
+/// Ratios of players with less confidence than this get a `?` suffix.
+const LOW_CONFIDENCE: f32 = 0.5;
+
+/// The abbreviation of each rank for `RankFormat::Short`. Ranks missing here are already short
+/// and shown as they are.
+const SHORT_RANKS: &[(&str, &str)] = &[
+    ("MVP++", "M++"),
+    ("MVP+", "M+"),
+    ("MVP", "M"),
+    ("VIP+", "V+"),
+    ("VIP", "V"),
+    ("ADMIN", "A"),
+    ("YOUTUBE", "YT"),
+    ("Default", "-"),
+];
+
+/// Picks how ranks are shown in every table printed afterwards.
+pub fn set_rank_format(format: RankFormat) {
+    *RANK_FORMAT.write().unwrap() = format;
+}
+
+/// Players with less confidence than `threshold` have their ratios hidden in every table
+/// printed afterwards, and aren't alerted about for them.
+pub fn set_confidence_threshold(threshold: f32) {
+    *CONFIDENCE_THRESHOLD.write().unwrap() = threshold;
+}
+
+pub fn confidence_threshold() -> f32 {
+    *CONFIDENCE_THRESHOLD.read().unwrap()
+}
+
+/// One of `p`'s ratios in `mode`, marked `?` when it comes from too few games to trust and
+/// replaced by `-?` below `threshold`. Players whose confidence is unknown are shown as they are.
+fn format_ratio(
+    p: &HypixelPlayer,
+    mode: Option<BedwarsMode>,
+    ratio: f32,
+    threshold: f32,
+) -> String {
+    if !p.ratios_trusted(mode, threshold) {
+        return "-?".to_string();
+    }
+    match p.confidence_in(mode) {
+        confidence if (0.0..LOW_CONFIDENCE).contains(&confidence) => format!("{ratio:.2}?"),
+        _ => format!("{ratio:.2}"),
+    }
+}
+
+fn ratio(p: &HypixelPlayer, mode: Option<BedwarsMode>, value: f32) -> String {
+    format_ratio(p, mode, value, confidence_threshold())
+}
+
+/// `rank` as shown in front of a name. Empty when nothing should be shown, which `Full` does
+/// for players without a rank.
+pub fn format_rank(rank: &str, format: RankFormat) -> String {
+    match format {
+        RankFormat::None => String::new(),
+        RankFormat::Full if rank == "Default" => String::new(),
+        RankFormat::Full => format!("[{rank}]"),
+        RankFormat::Short => SHORT_RANKS
+            .iter()
+            .find(|(full, _)| *full == rank)
+            .map_or(rank, |(_, short)| short)
+            .to_string(),
+    }
+}
+
+/// The name with the rank in front, as `rank-format` asks for.
+pub fn ranked_name(p: &HypixelPlayer) -> String {
+    match format_rank(&p.rank, *RANK_FORMAT.read().unwrap()) {
+        rank if rank.is_empty() => p.name.clone(),
+        rank => format!("{rank} {}", p.name),
+    }
+}
+
+/// Rank-colored name, prefixed with any tags that apply to the player.
+fn tagged_name(p: &HypixelPlayer) -> (String, usize) {
+    let name = ranked_name(p);
+    let mut width = name.chars().count();
+    let mut tagged = Colored::new(name, color::rank_color(&p.rank)).to_string();
+
+    if p.is_content_creator {
+        tagged = format!("{tagged} 🎥");
+        // The emoji takes two columns
+        width += 3;
+    }
+
+    if p.is_grinder() {
+        tagged = format!("{tagged} 📋");
+        width += 3;
+    }
+
+    if p.has_active_booster {
+        tagged = format!("{tagged} 🚀");
+        width += 3;
+    }
+
+    if p.is_currently_online() {
+        let tag = text("tag.online");
+        tagged = format!("{tagged} {tag}");
+        width += tag.chars().count() + 1;
+    }
+
+    if p.is_friend {
+        let tag = text("tag.friend");
+        tagged = format!("{tag} {tagged}");
+        width += tag.chars().count() + 1;
+    }
+
+    (tagged, width)
+}
+
+/// Pads `text` to `width` columns based on its visible length, which excludes color codes.
+fn padded(text: impl Display, visible: usize, width: usize) -> String {
+    format!("{text}{}", " ".repeat(width.saturating_sub(visible)))
+}
+
+/// Like `padded`, but right-aligns `text` within the column.
+fn right_aligned(text: impl Display, visible: usize, width: usize) -> String {
+    format!("{}{text}", " ".repeat(width.saturating_sub(visible)))
+}
+
+fn colored_fkdr(p: &HypixelPlayer, mode: Option<BedwarsMode>) -> Colored<String> {
+    let fkdr = p.fkdr_in(mode);
+    Colored::new(ratio(p, mode, fkdr), color::fkdr_color(fkdr))
+}
+
+/// Groups digits in threes, e.g. `12,345`.
+fn thousands(n: i32) -> String {
+    let digits = n.unsigned_abs().to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    if n < 0 {
+        grouped.insert(0, '-');
+    }
+    grouped
+}
+
+/// One `KEY:value` line per player, stable enough to pipe into other tools. Values are plain:
+/// no colors or tags, and ratios are printed even from few games (`-1` when unknown).
+pub fn format_player_compact(p: &HypixelPlayer, mode: Option<BedwarsMode>) -> String {
+    let star = StarDisplay {
+        level: p.level,
+        color_enabled: false,
+    };
+    format!(
+        "{} {star} FKDR:{:.2} WLR:{:.2} WS:{} FIN:{} BEDS:{} BBLR:{:.2} IDX:{:.0}",
+        ranked_name(p),
+        p.fkdr_in(mode),
+        p.wlr_in(mode),
+        p.winstreak,
+        p.final_kills,
+        p.beds_broken_in(mode),
+        p.bblr_in(mode),
+        p.index_in(mode),
+    )
+}
+
+/// A table of the whole lobby with one row per player. `normalizer` adds a `NORM` column with
+/// each FKDR relative to the reference player.
+pub fn format_player_verbose(
+    players: &[HypixelPlayer],
+    mode: Option<BedwarsMode>,
+    normalizer: Option<&StatNormalizer>,
+    show_arcade: bool,
+) -> String {
+    let mut table = format!(
+        "{:<NAME_WIDTH$}{:<STAR_WIDTH$}{:>8}{:>8}{:>8}{:>6}{:>9}{:>8}{:>8}{:>10}{:>8}{:>12}{:>11}",
+        text("column.name"),
+        text("column.star"),
+        text("column.fkdr"),
+        text("column.rfkdr"),
+        text("column.wlr"),
+        text("column.ws"),
+        text("column.finals"),
+        text("column.beds"),
+        text("column.bblr"),
+        text("column.index"),
+        text("column.recent"),
+        text("column.karma"),
+        text("column.style"),
+    );
+    if normalizer.is_some() {
+        table.push_str(&format!("{:>8}", text("column.norm")));
+    }
+    if show_arcade {
+        table.push_str(&format!("{:>11}", text("column.arcade_kdr")));
+    }
+    table.push('\n');
+
+    for p in players {
+        let (name, name_width) = tagged_name(p);
+        let star_width = format!("[{}✫]", p.level).chars().count();
+        let fkdr = p.fkdr_in(mode);
+        // INDEX and NORM are built from the FKDR, so they are hidden along with it
+        let trusted = p.ratios_trusted(mode, confidence_threshold());
+        let index = if trusted {
+            format!("{:.0}", p.index_in(mode))
+        } else {
+            "-?".to_string()
+        };
+
+        table.push_str(&format!(
+            "{}{}{}{:>8}{:>8}{:>6}{:>9}{:>8}{:>8}{:>10}{:>8}{:>12}{:>11}",
+            padded(name, name_width, NAME_WIDTH),
+            padded(p.level_display(), star_width, STAR_WIDTH),
+            right_aligned(colored_fkdr(p, mode), ratio(p, mode, fkdr).len(), 8),
+            ratio(p, None, p.recent_fkdr),
+            ratio(p, mode, p.wlr_in(mode)),
+            p.winstreak,
+            p.final_kills,
+            p.beds_broken_in(mode),
+            ratio(p, mode, p.bblr_in(mode)),
+            index,
+            p.games_last_24h,
+            thousands(p.karma),
+            p.playstyle.label(),
+        ));
+        if let Some(normalizer) = normalizer {
+            let norm = match normalizer.normalize(p).fkdr {
+                _ if !trusted => "-?".to_string(),
+                Some(fkdr) => format!("{fkdr:.1}x"),
+                None => "-".to_string(),
+            };
+            table.push_str(&format!("{norm:>8}"));
+        }
+        if show_arcade {
+            let kdr = match p.arcade.kdr() {
+                Some(kdr) => format!("{kdr:.2}"),
+                None => "-".to_string(),
+            };
+            table.push_str(&format!("{kdr:>11}"));
+        }
+        if p.network_xp_to_next_level >= 0 {
+            table.push_str(&format!(
+                "  {}: {}",
+                text("label.xp_to_next"),
+                thousands(p.network_xp_to_next_level)
+            ));
+        }
+        if p.prestige_completion_pct >= 0.0 {
+            table.push_str(&format!(
+                "  {}: {:.1}%",
+                text("label.prestige"),
+                p.prestige_completion_pct
+            ));
+        }
+        table.push('\n');
+    }
+
+    table
+}
+
+/// The verbose table split into one section per team, most threatening team first, for when
+/// team colors are known.
+pub fn format_teams_verbose(
+    groups: &[PlayerGroup],
+    mode: Option<BedwarsMode>,
+    normalizer: Option<&StatNormalizer>,
+    show_arcade: bool,
+) -> String {
+    let mut groups: Vec<&PlayerGroup> = groups.iter().collect();
+    groups.sort_by(|a, b| b.group_threat_score().total_cmp(&a.group_threat_score()));
+
+    groups
+        .into_iter()
+        .map(|group| {
+            format!(
+                "{}  FKDR:{:.2} {}:{}-{} {}:{:.0}\n{}",
+                text("team.header").replace("{team}", group.color.label()),
+                group.average_fkdr(),
+                text("team.stars"),
+                group.min_level(),
+                group.max_level(),
+                text("team.threat"),
+                group.group_threat_score(),
+                format_player_verbose(&group.members, mode, normalizer, show_arcade)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_are_abbreviated() {
+        for (full, short) in SHORT_RANKS {
+            assert_eq!(format_rank(full, RankFormat::Short), *short);
+            // Every abbreviation leads back to one rank
+            let ranks = SHORT_RANKS
+                .iter()
+                .filter(|(_, other)| other == short)
+                .map(|(rank, _)| *rank)
+                .collect::<Vec<_>>();
+            assert_eq!(ranks, [*full]);
+            assert_eq!(format_rank(full, RankFormat::None), "");
+        }
+
+        assert_eq!(format_rank("MVP++", RankFormat::Full), "[MVP++]");
+        assert_eq!(format_rank("Default", RankFormat::Full), "");
+        assert_eq!(format_rank("GM", RankFormat::Short), "GM");
+    }
+
+    #[test]
+    fn ratios_from_few_games_are_marked() {
+        use crate::hypixel::HypixelPlayerBuilder;
+
+        let player = |confidence| {
+            HypixelPlayerBuilder::new("Steve", "uuid")
+                .confidence(confidence)
+                .build()
+        };
+
+        assert_eq!(format_ratio(&player(1.0), None, 3.5, 0.3), "3.50");
+        assert_eq!(format_ratio(&player(0.4), None, 3.5, 0.3), "3.50?");
+        assert_eq!(format_ratio(&player(0.02), None, 10.0, 0.3), "-?");
+        assert_eq!(format_ratio(&player(-1.0), None, 3.5, 0.3), "3.50");
+
+        // Ten games at 10.0 FKDR: INDEX and NORM are hidden along with the ratios
+        let outlier = HypixelPlayerBuilder::new("Steve", "uuid")
+            .level(100)
+            .fkdr(10.0)
+            .confidence(0.02)
+            .build();
+        let normalizer =
+            StatNormalizer::new(HypixelPlayerBuilder::new("Me", "uuid").fkdr(2.0).build());
+        let table = format_player_verbose(&[outlier], None, Some(&normalizer), false);
+        let row = table.lines().nth(1).unwrap();
+        assert!(!row.contains("10000") && !row.contains("5.0x"), "{row}");
+        assert_eq!(row.matches("-?").count(), 6, "{row}");
+    }
+
+    #[test]
+    fn compact_lines_are_plain() {
+        use crate::hypixel::HypixelPlayerBuilder;
+
+        let player = HypixelPlayerBuilder::new("Steve", "uuid")
+            .level(1_200)
+            .fkdr(12.0)
+            .confidence(0.02)
+            .build();
+        let line = format_player_compact(&player, None);
+
+        assert!(line.starts_with("Steve [1200✫] FKDR:12.00 "), "{line}");
+        assert!(!line.contains('\x1b') && !line.contains('?'), "{line}");
+    }
+}
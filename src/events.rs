@@ -0,0 +1,114 @@
+use serde_derive::Deserialize;
+use std::sync::LazyLock;
+
+use crate::hypixel::HypixelPlayer;
+
+/// Known double XP events, as epoch milliseconds, taken from Hypixel's event announcements. The
+/// file's `source` says where, the announcements give dates but not exact start and end times.
+static BUNDLED_EVENTS: &[u8] = include_bytes!("hypixel_events.json");
+
+static EVENT_PERIODS: LazyLock<Vec<(i64, i64)>> = LazyLock::new(|| {
+    serde_json::from_slice::<EventList>(BUNDLED_EVENTS)
+        .expect("hypixel_events.json is valid")
+        .events
+        .into_iter()
+        .map(|period| (period.start, period.end))
+        .collect()
+});
+
+#[derive(Deserialize)]
+struct EventList {
+    events: Vec<EventPeriod>,
+}
+
+#[derive(Deserialize)]
+struct EventPeriod {
+    start: i64,
+    end: i64,
+}
+
+/// A player's XP based stats with what double XP events likely added taken back out. Stats like
+/// final kills and wins don't depend on XP, so they're left alone. Unknown stats stay `-1`.
+#[derive(Debug, PartialEq)]
+pub struct EventAdjustedPlayer {
+    pub network_xp: i32,
+    pub level: i32,
+    /// How much of the player's account lifetime fell inside an event, from 0 to 1.
+    pub event_share: f32,
+}
+
+/// Scales XP based stats down for the double XP events a player's account lived through.
+pub struct EventNormalizer {
+    event_periods: Vec<(i64, i64)>,
+}
+
+impl EventNormalizer {
+    /// Uses the event windows bundled with the binary.
+    pub fn bundled() -> Self {
+        EventNormalizer {
+            event_periods: EVENT_PERIODS.clone(),
+        }
+    }
+
+    pub fn normalize(&self, player: &HypixelPlayer) -> EventAdjustedPlayer {
+        normalize_for_events(player, &self.event_periods)
+    }
+}
+
+/// Assumes the player played evenly between their first and last login, so a share `s` of that
+/// time spent in events earned stats at `1 + s` times the normal rate. Players without both login
+/// times are left as they are.
+pub fn normalize_for_events(
+    stats: &HypixelPlayer,
+    event_periods: &[(i64, i64)],
+) -> EventAdjustedPlayer {
+    let event_share = match (stats.first_login, stats.last_login) {
+        (Some(first), Some(last)) if last > first => {
+            let in_events: i64 = event_periods
+                .iter()
+                .map(|&(start, end)| (end.min(last) - start.max(first)).max(0))
+                .sum();
+            in_events as f32 / (last - first) as f32
+        }
+        _ => 0.0,
+    };
+    let scale = |stat: i32| {
+        if stat < 0 {
+            stat
+        } else {
+            (stat as f32 / (1.0 + event_share)).round() as i32
+        }
+    };
+
+    EventAdjustedPlayer {
+        network_xp: scale(stats.network_xp),
+        level: scale(stats.level),
+        event_share,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hypixel::HypixelPlayerBuilder;
+
+    #[test]
+    fn xp_earned_during_events_is_scaled_down() {
+        assert!(!EventNormalizer::bundled().event_periods.is_empty());
+
+        // A quarter of the account's lifetime was a double XP event
+        let player = HypixelPlayerBuilder::new("Steve", "uuid")
+            .level(500)
+            .first_login(0)
+            .last_login(400)
+            .build();
+        let adjusted = normalize_for_events(&player, &[(100, 150), (350, 500)]);
+
+        assert_eq!(adjusted.event_share, 0.25);
+        assert_eq!(adjusted.level, 400);
+        assert_eq!(adjusted.network_xp, -1);
+
+        let never_logged = HypixelPlayerBuilder::new("Alex", "uuid").level(500).build();
+        assert_eq!(normalize_for_events(&never_logged, &[(0, 1)]).level, 500);
+    }
+}
@@ -0,0 +1,259 @@
+use anyhow::{Context, Result};
+use log::{error, warn};
+use serde_json::{json, Value};
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::{fs, task::JoinSet};
+
+use crate::{display, hypixel::HypixelPlayer, lobby::LobbySnapshot, logging, output::BoxFuture};
+
+/// Values accepted in the `exporters` config array.
+pub const EXPORTERS: [&str; 3] = ["json", "csv", "markdown"];
+
+/// Saves a looked up lobby somewhere outside the overlay's own output. Boxed futures keep the
+/// trait usable as `dyn StatsExporter`, like `OutputSink`.
+pub trait StatsExporter: Send + Sync {
+    fn export<'a>(&'a self, snapshot: &'a LobbySnapshot) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Builds the exporters named in the `exporters` config value, skipping any this build can't
+/// provide.
+pub fn from_config(names: &[String]) -> Vec<Arc<dyn StatsExporter>> {
+    let mut exporters: Vec<Arc<dyn StatsExporter>> = Vec::new();
+    for name in names {
+        match name.as_str() {
+            "json" => exporters.push(Arc::new(JsonExporter)),
+            #[cfg(feature = "csv-export")]
+            "csv" => exporters.push(Arc::new(CsvExporter::new(PathBuf::from(CSV_PATH)))),
+            #[cfg(not(feature = "csv-export"))]
+            "csv" => warn!("The csv exporter needs the csv-export feature, skipping it"),
+            "markdown" => exporters.push(Arc::new(MarkdownExporter)),
+            other => warn!("Unknown exporter {other}, skipping it"),
+        }
+    }
+    exporters
+}
+
+/// Runs every exporter on `snapshot` at once. Failures are only logged so one broken exporter
+/// doesn't hold back the others.
+pub async fn export_all(exporters: &[Arc<dyn StatsExporter>], snapshot: LobbySnapshot) {
+    let snapshot = Arc::new(snapshot);
+    let mut exports = JoinSet::new();
+    for exporter in exporters {
+        let exporter = Arc::clone(exporter);
+        let snapshot = Arc::clone(&snapshot);
+        exports.spawn(logging::in_current_lobby(async move {
+            exporter.export(&snapshot).await
+        }));
+    }
+
+    while let Some(result) = exports.join_next().await {
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!("Error while exporting lobby: {e:#}"),
+            Err(e) => error!("Export task failed: {e}"),
+        }
+    }
+}
+
+/// Epoch milliseconds, used to name one file per lobby.
+fn timestamp() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default()
+}
+
+pub fn player_json(player: &HypixelPlayer) -> Value {
+    json!({
+        "name": player.name,
+        "uuid": player.uuid,
+        "rank": player.rank,
+        "level": player.level,
+        "fkdr": player.fkdr,
+        "wlr": player.wlr,
+        "final_kills": player.final_kills,
+        "wins": player.wins,
+        "beds_broken": player.bed_break,
+        "winstreak": player.winstreak,
+        "confidence": player.confidence,
+    })
+}
+
+/// Writes each lobby to its own `lobby_<ts>.json`.
+pub struct JsonExporter;
+
+impl StatsExporter for JsonExporter {
+    fn export<'a>(&'a self, snapshot: &'a LobbySnapshot) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let path = PathBuf::from(format!("lobby_{}.json", timestamp()));
+            let document = json!({
+                "lobby_id": snapshot.lobby_id,
+                "instance": snapshot.source_instance,
+                "players": snapshot.players.iter().map(player_json).collect::<Vec<_>>(),
+            });
+            fs::write(&path, serde_json::to_string_pretty(&document)?)
+                .await
+                .with_context(|| format!("Failed to write {}", path.display()))
+        })
+    }
+}
+
+#[cfg(feature = "csv-export")]
+const CSV_PATH: &str = "stats.csv";
+
+#[cfg(feature = "csv-export")]
+const CSV_HEADER: &str =
+    "timestamp,name,uuid,rank,level,fkdr,wlr,final_kills,wins,beds_broken,winstreak,confidence";
+
+/// Appends one row per player to `stats.csv`, writing the header when the file is new.
+#[cfg(feature = "csv-export")]
+pub struct CsvExporter {
+    path: PathBuf,
+    /// Held while appending so rows from two lobbies never interleave.
+    lock: tokio::sync::Mutex<()>,
+}
+
+#[cfg(feature = "csv-export")]
+impl CsvExporter {
+    pub fn new(path: PathBuf) -> Self {
+        CsvExporter {
+            path,
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+}
+
+#[cfg(feature = "csv-export")]
+impl StatsExporter for CsvExporter {
+    fn export<'a>(&'a self, snapshot: &'a LobbySnapshot) -> BoxFuture<'a, Result<()>> {
+        use tokio::io::AsyncWriteExt;
+
+        Box::pin(async move {
+            let _guard = self.lock.lock().await;
+            let mut rows = String::new();
+            if !matches!(fs::try_exists(&self.path).await, Ok(true)) {
+                rows.push_str(CSV_HEADER);
+                rows.push('\n');
+            }
+            let timestamp = timestamp();
+            for player in &snapshot.players {
+                rows.push_str(&format!(
+                    "{timestamp},{},{},{},{},{:.2},{:.2},{},{},{},{},{:.2}\n",
+                    player.name,
+                    player.uuid,
+                    player.rank,
+                    player.level,
+                    player.fkdr,
+                    player.wlr,
+                    player.final_kills,
+                    player.wins,
+                    player.bed_break,
+                    player.winstreak,
+                    player.confidence,
+                ));
+            }
+
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .await
+                .with_context(|| format!("Failed to open {}", self.path.display()))?;
+            file.write_all(rows.as_bytes())
+                .await
+                .with_context(|| format!("Failed to write to {}", self.path.display()))
+        })
+    }
+}
+
+/// Writes each lobby to `lobby_<ts>.md` as a GitHub Flavored Markdown table, for pasting into
+/// Discord or GitHub.
+pub struct MarkdownExporter;
+
+impl StatsExporter for MarkdownExporter {
+    fn export<'a>(&'a self, snapshot: &'a LobbySnapshot) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let path = PathBuf::from(format!("lobby_{}.md", timestamp()));
+            fs::write(&path, markdown_table(&snapshot.players))
+                .await
+                .with_context(|| format!("Failed to write {}", path.display()))
+        })
+    }
+}
+
+pub fn markdown_table(players: &[HypixelPlayer]) -> String {
+    let mut table = String::from(
+        "| Player | Level | FKDR | WLR | Finals | Wins | Beds | WS |\n\
+         |---|--:|--:|--:|--:|--:|--:|--:|\n",
+    );
+    for player in players {
+        table.push_str(&format!(
+            "| {} | {} | {:.2} | {:.2} | {} | {} | {} | {} |\n",
+            display::ranked_name(player),
+            player.level,
+            player.fkdr,
+            player.wlr,
+            player.final_kills,
+            player.wins,
+            player.bed_break,
+            player.winstreak,
+        ));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hypixel::HypixelPlayerBuilder;
+
+    #[test]
+    fn markdown_table_has_a_row_per_player() {
+        let players = [
+            HypixelPlayerBuilder::new("Steve", "069a79f444e94726a5befca90e38aaf5")
+                .rank("MVP+")
+                .level(412)
+                .fkdr(5.5)
+                .final_kills(9001)
+                .winstreak(12)
+                .build(),
+            HypixelPlayer::unknown("Nick"),
+        ];
+
+        let table = markdown_table(&players);
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[0],
+            "| Player | Level | FKDR | WLR | Finals | Wins | Beds | WS |"
+        );
+        assert!(lines[2].starts_with("| [MVP+] Steve | 412 | 5.50 |"));
+        assert!(lines[2].ends_with("| 9001 | -1 | -1 | 12 |"));
+        assert!(lines[3].starts_with("| Nick | -1 |"));
+    }
+
+    #[test]
+    fn redacted_exports_hide_names_and_uuids() {
+        let player = HypixelPlayerBuilder::new("Steve", "069a79f444e94726a5befca90e38aaf5")
+            .level(412)
+            .fkdr(5.5)
+            .build();
+        let redacted = player.redact();
+
+        let json = player_json(&redacted).to_string();
+        let table = markdown_table(std::slice::from_ref(&redacted));
+        for output in [&json, &table] {
+            assert!(!output.contains("Steve"));
+            assert!(!output.contains("069a79f4"));
+        }
+        assert!(redacted.name.starts_with("PLAYER_"));
+        assert_eq!(redacted.name.len(), "PLAYER_".len() + 8);
+        assert_eq!(redacted.fkdr, 5.5);
+        assert_eq!(player.redact().name, redacted.name);
+    }
+}
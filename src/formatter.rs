@@ -0,0 +1,199 @@
+use crate::{
+    config::LogFormat,
+    display, export, glossary,
+    hypixel::{self, BedwarsMode, HypixelPlayer},
+    lobby::LobbySnapshot,
+    normalizer::StatNormalizer,
+    team,
+};
+
+/// Turns looked up players into the overlay's output. `format_player` runs as each player's
+/// stats arrive and `format_lobby` once the whole lobby is done; either may return an empty
+/// string to print nothing at that point.
+pub trait Formatter: Send + Sync {
+    fn format_lobby(&self, snapshot: &LobbySnapshot) -> String;
+    fn format_player(&self, player: &HypixelPlayer, mode: Option<BedwarsMode>) -> String;
+}
+
+/// Builds the formatter picked by `log-format`. `normalizer` and `show_arcade` add the optional
+/// columns of the table.
+pub fn from_config(
+    format: LogFormat,
+    normalizer: Option<StatNormalizer>,
+    show_arcade: bool,
+) -> Box<dyn Formatter> {
+    match format {
+        LogFormat::Compact => Box::new(CompactFormatter),
+        LogFormat::Verbose => Box::new(TableFormatter {
+            normalizer,
+            show_arcade,
+        }),
+        LogFormat::Debug => Box::new(DebugFormatter),
+        LogFormat::Json => Box::new(JsonFormatter),
+        LogFormat::Markdown => Box::new(MarkdownFormatter),
+    }
+}
+
+// Nothing in the overlay builds a custom formatter, it is there for forks with their own output
+#[allow(dead_code)]
+impl dyn Formatter {
+    /// A formatter that prints the whole lobby with `f` and nothing per player.
+    pub fn custom(
+        f: impl Fn(&LobbySnapshot) -> String + Send + Sync + 'static,
+    ) -> Box<dyn Formatter> {
+        Box::new(CustomFormatter(f))
+    }
+}
+
+struct CustomFormatter<F>(F);
+
+impl<F: Fn(&LobbySnapshot) -> String + Send + Sync> Formatter for CustomFormatter<F> {
+    fn format_lobby(&self, snapshot: &LobbySnapshot) -> String {
+        (self.0)(snapshot)
+    }
+
+    fn format_player(&self, _player: &HypixelPlayer, _mode: Option<BedwarsMode>) -> String {
+        String::new()
+    }
+}
+
+/// The verbose table of the whole lobby, most threatening player first. Once teams are known
+/// from in-game chat, players are grouped by team with the most threatening team first.
+pub struct TableFormatter {
+    normalizer: Option<StatNormalizer>,
+    show_arcade: bool,
+}
+
+impl Formatter for TableFormatter {
+    fn format_lobby(&self, snapshot: &LobbySnapshot) -> String {
+        let mut players = snapshot.players.clone();
+        hypixel::sort_by_threat(&mut players, display::confidence_threshold());
+        let (groups, unassigned) = team::group_by_team(&players, &snapshot.teams);
+
+        let mut table = String::new();
+        if !groups.is_empty() {
+            table = display::format_teams_verbose(
+                &groups,
+                snapshot.mode,
+                self.normalizer.as_ref(),
+                self.show_arcade,
+            );
+        }
+        if !unassigned.is_empty() {
+            if !table.is_empty() {
+                table.push('\n');
+            }
+            table.push_str(&display::format_player_verbose(
+                &unassigned,
+                snapshot.mode,
+                self.normalizer.as_ref(),
+                self.show_arcade,
+            ));
+        }
+        table + &glossary::footer()
+    }
+
+    fn format_player(&self, _player: &HypixelPlayer, _mode: Option<BedwarsMode>) -> String {
+        String::new()
+    }
+}
+
+/// One `KEY:value` line per player as soon as their stats arrive.
+pub struct CompactFormatter;
+
+impl Formatter for CompactFormatter {
+    fn format_lobby(&self, _snapshot: &LobbySnapshot) -> String {
+        String::new()
+    }
+
+    fn format_player(&self, player: &HypixelPlayer, mode: Option<BedwarsMode>) -> String {
+        display::format_player_compact(player, mode)
+    }
+}
+
+/// The raw `HypixelPlayer` debug dump of each player.
+pub struct DebugFormatter;
+
+impl Formatter for DebugFormatter {
+    fn format_lobby(&self, _snapshot: &LobbySnapshot) -> String {
+        String::new()
+    }
+
+    fn format_player(&self, player: &HypixelPlayer, _mode: Option<BedwarsMode>) -> String {
+        format!("{player:#?}")
+    }
+}
+
+/// One JSON object per player, the same fields the json exporter writes, for other programs to
+/// read line by line.
+pub struct JsonFormatter;
+
+impl Formatter for JsonFormatter {
+    fn format_lobby(&self, _snapshot: &LobbySnapshot) -> String {
+        String::new()
+    }
+
+    fn format_player(&self, player: &HypixelPlayer, _mode: Option<BedwarsMode>) -> String {
+        export::player_json(player).to_string()
+    }
+}
+
+/// The markdown exporter's table of the whole lobby.
+pub struct MarkdownFormatter;
+
+impl Formatter for MarkdownFormatter {
+    fn format_lobby(&self, snapshot: &LobbySnapshot) -> String {
+        export::markdown_table(&snapshot.players)
+    }
+
+    fn format_player(&self, _player: &HypixelPlayer, _mode: Option<BedwarsMode>) -> String {
+        String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hypixel::HypixelPlayerBuilder;
+
+    #[test]
+    fn formatters_print_per_player_or_per_lobby() {
+        let steve = HypixelPlayerBuilder::new("Steve", "uuid")
+            .level(412)
+            .fkdr(5.5)
+            .build();
+        let snapshot = LobbySnapshot {
+            names: vec!["Steve".to_string()],
+            source_instance: None,
+            players: vec![steve.clone()],
+            lobby_id: String::new(),
+            mode: None,
+            teams: Default::default(),
+        };
+
+        let compact = from_config(LogFormat::Compact, None, false);
+        assert!(compact.format_player(&steve, None).contains("FKDR:"));
+        assert!(compact.format_lobby(&snapshot).is_empty());
+
+        let json = from_config(LogFormat::Json, None, false);
+        let line: serde_json::Value =
+            serde_json::from_str(&json.format_player(&steve, None)).unwrap();
+        assert_eq!(line["level"], 412);
+
+        let markdown = from_config(LogFormat::Markdown, None, false);
+        assert!(markdown.format_player(&steve, None).is_empty());
+        assert_eq!(markdown.format_lobby(&snapshot).lines().count(), 3);
+
+        let table = from_config(LogFormat::Verbose, None, false);
+        assert!(!table.format_lobby(&snapshot).contains("Red"));
+        let in_game = LobbySnapshot {
+            teams: [("steve".to_string(), team::TeamColor::Red)].into(),
+            ..snapshot.clone()
+        };
+        assert!(table.format_lobby(&in_game).contains("Red"));
+
+        let custom = <dyn Formatter>::custom(|snapshot| snapshot.names.join(" "));
+        assert_eq!(custom.format_lobby(&snapshot), "Steve");
+        assert!(custom.format_player(&steve, None).is_empty());
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::{Context, Result};
+use log::{error, info};
+use serde_derive::Deserialize;
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use crate::{api::HypixelApiClient, comparable_uuid, Uuid};
+
+const FRIENDS_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Deserialize)]
+struct ApiFriends {
+    records: Vec<ApiFriendRecord>,
+}
+
+#[derive(Deserialize)]
+struct ApiFriendRecord {
+    #[serde(rename = "uuidSender")]
+    sender: Uuid,
+    #[serde(rename = "uuidReceiver")]
+    receiver: Uuid,
+}
+
+pub async fn get_friends(uuid: &Uuid, client: &HypixelApiClient) -> Result<Vec<Uuid>> {
+    let body = client.get("v2/friends", uuid).await?;
+    let friends: ApiFriends = serde_json::from_str(&body)
+        .with_context(|| format!("Failed to parse friends list for {uuid}"))?;
+
+    let owner = comparable_uuid(uuid);
+    Ok(friends
+        .records
+        .into_iter()
+        .map(|record| {
+            if comparable_uuid(&record.sender) == owner {
+                comparable_uuid(&record.receiver)
+            } else {
+                comparable_uuid(&record.sender)
+            }
+        })
+        .collect())
+}
+
+/// The configured user's friends, fetched lazily and refreshed once `FRIENDS_TTL` has passed.
+pub struct FriendsList {
+    owner: Uuid,
+    friends: HashSet<Uuid>,
+    fetched_at: Option<Instant>,
+}
+
+impl FriendsList {
+    pub fn new(owner: Uuid) -> Self {
+        FriendsList {
+            owner,
+            friends: HashSet::new(),
+            fetched_at: None,
+        }
+    }
+
+    /// Returns the cached friends, refetching them first if the cache is empty or stale.
+    /// A failed refresh keeps the previous list rather than forgetting every friend.
+    pub async fn get(&mut self, client: &HypixelApiClient) -> &HashSet<Uuid> {
+        let stale = self
+            .fetched_at
+            .is_none_or(|fetched_at| fetched_at.elapsed() > FRIENDS_TTL);
+
+        if stale {
+            match get_friends(&self.owner, client).await {
+                Ok(friends) => {
+                    info!("Loaded {} friends for {}", friends.len(), self.owner);
+                    self.friends = friends.into_iter().collect();
+                    self.fetched_at = Some(Instant::now());
+                }
+                Err(e) => error!("Error while getting friends list: {e}"),
+            }
+        }
+
+        &self.friends
+    }
+}
+
+pub fn is_friend(friends: &HashSet<Uuid>, uuid: &str) -> bool {
+    friends.contains(&comparable_uuid(uuid))
+}
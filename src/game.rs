@@ -0,0 +1,152 @@
+use log::{info, warn};
+use std::{
+    collections::HashMap,
+    ops::{Add, AddAssign, Deref},
+};
+
+use crate::{notification, team::TeamColor};
+
+/// What has happened in the current game so far, built purely from chat messages.
+#[derive(Default, Debug)]
+pub struct GameState {
+    /// Team name to whether its bed is still standing. `Your` is the user's own team.
+    pub beds_remaining: HashMap<String, bool>,
+    /// Players that were final killed, in order.
+    pub eliminations: Vec<String>,
+    /// The team of every player seen chatting in game, keyed by lowercased name.
+    pub teams: HashMap<String, TeamColor>,
+}
+
+impl GameState {
+    pub fn bed_destroyed(&mut self, team: &str, destroyer: &str) {
+        self.beds_remaining.insert(team.to_string(), false);
+
+        if team.eq_ignore_ascii_case("your") {
+            warn!("YOUR BED WAS DESTROYED by {destroyer}");
+            notification::send(
+                "Bed destroyed",
+                &format!("Your bed was destroyed by {destroyer}"),
+            );
+        } else {
+            info!("{team} bed was destroyed by {destroyer}");
+        }
+    }
+
+    pub fn team_member(&mut self, team: TeamColor, player: &str) {
+        self.teams.insert(player.to_lowercase(), team);
+    }
+
+    pub fn final_kill(&mut self, victim: &str) {
+        self.eliminations.push(victim.to_string());
+        info!(
+            "{victim} was final killed ({} eliminated so far)",
+            self.eliminations.len()
+        );
+    }
+}
+
+/// What one Minecraft instance saw over the whole session, across every game.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionStats {
+    /// Bedwars games joined, counted by mode announcements.
+    pub games: i32,
+    /// `/who` responses read.
+    pub lobbies: i32,
+    pub beds_destroyed: i32,
+    pub final_kills: i32,
+}
+
+impl Add for SessionStats {
+    type Output = SessionStats;
+
+    fn add(self, other: SessionStats) -> SessionStats {
+        SessionStats {
+            games: self.games.saturating_add(other.games),
+            lobbies: self.lobbies.saturating_add(other.lobbies),
+            beds_destroyed: self.beds_destroyed.saturating_add(other.beds_destroyed),
+            final_kills: self.final_kills.saturating_add(other.final_kills),
+        }
+    }
+}
+
+impl AddAssign for SessionStats {
+    fn add_assign(&mut self, other: SessionStats) {
+        *self = *self + other;
+    }
+}
+
+/// The `SessionStats` of every watched instance, dereferencing to their sum.
+pub struct GlobalSessionStats {
+    instances: Vec<SessionStats>,
+    total: SessionStats,
+}
+
+impl FromIterator<SessionStats> for GlobalSessionStats {
+    fn from_iter<I: IntoIterator<Item = SessionStats>>(iter: I) -> Self {
+        let instances: Vec<SessionStats> = iter.into_iter().collect();
+        let total = instances
+            .iter()
+            .fold(SessionStats::default(), |total, &stats| total + stats);
+        GlobalSessionStats { instances, total }
+    }
+}
+
+impl Deref for GlobalSessionStats {
+    type Target = SessionStats;
+
+    fn deref(&self) -> &SessionStats {
+        &self.total
+    }
+}
+
+impl GlobalSessionStats {
+    /// The line logged at shutdown.
+    pub fn summary(&self) -> String {
+        format!(
+            "Across {} instance(s): {} games, {} lobbies, {} beds destroyed, {} final kills",
+            self.instances.len(),
+            self.games,
+            self.lobbies,
+            self.beds_destroyed,
+            self.final_kills
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_stats_add_up_without_overflowing() {
+        let a = SessionStats {
+            games: 3,
+            lobbies: 5,
+            beds_destroyed: 2,
+            final_kills: i32::MAX,
+        };
+        let b = SessionStats {
+            games: 1,
+            lobbies: 2,
+            beds_destroyed: 4,
+            final_kills: 10,
+        };
+
+        let expected = SessionStats {
+            games: 4,
+            lobbies: 7,
+            beds_destroyed: 6,
+            final_kills: i32::MAX,
+        };
+        assert_eq!(a + b, expected);
+        let mut sum = a;
+        sum += b;
+        assert_eq!(sum, expected);
+
+        let global: GlobalSessionStats = [a, b].into_iter().collect();
+        assert_eq!(*global, expected);
+        assert!(global
+            .summary()
+            .starts_with("Across 2 instance(s): 4 games, 7 lobbies"));
+    }
+}
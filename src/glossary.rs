@@ -0,0 +1,147 @@
+use anyhow::Result;
+use std::sync::RwLock;
+
+use crate::i18n::{t, text, LANGUAGES};
+
+/// Every stat column the overlay prints, keyed by the i18n id of its header, with how it is
+/// worked out. Both `glossary` and `--explain` read from here, and show the header in the
+/// configured language.
+pub static GLOSSARY: &[(&str, &str)] = &[
+    ("column.star", "Bedwars level, from Bedwars experience"),
+    (
+        "column.fkdr",
+        "Final Kills / Final Deaths (higher = more skilled)",
+    ),
+    (
+        "column.rfkdr",
+        "FKDR over the last 100 games, lifetime FKDR when Hypixel has none (higher = in form)",
+    ),
+    ("column.wlr", "Wins / Losses (higher = wins more games)"),
+    (
+        "column.ws",
+        "Current winstreak, only visible if the player shares it",
+    ),
+    ("column.finals", "Lifetime final kills"),
+    ("column.beds", "Lifetime beds broken"),
+    (
+        "column.bblr",
+        "Beds Broken / Beds Lost (higher = more aggressive)",
+    ),
+    (
+        "column.index",
+        "Star x FKDR^2, a single threat score (higher = more dangerous)",
+    ),
+    ("column.recent", "Games played in the last 24 hours"),
+    (
+        "column.karma",
+        "Hypixel karma, earned mostly by playing and tipping",
+    ),
+    (
+        "column.style",
+        "Sniper when FKDR > 4 and BBLR < 2, rusher when BBLR > 4 and FKDR < 3, else balanced",
+    ),
+    (
+        "column.norm",
+        "Player FKDR / your FKDR (1.5x = one and a half times yours)",
+    ),
+    (
+        "column.arcade_kdr",
+        "Arcade Kills / Arcade Deaths, summed over supported games",
+    ),
+];
+
+/// The column ids `--explain` asked for, described under every verbose table.
+static EXPLAINED: RwLock<Vec<&'static str>> = RwLock::new(Vec::new());
+
+/// The column id whose header is `header` in any language, ignoring case.
+fn column_id(header: &str) -> Option<&'static str> {
+    let header = header.to_lowercase();
+    GLOSSARY.iter().map(|(id, _)| *id).find(|id| {
+        LANGUAGES
+            .iter()
+            .any(|lang| t(id, lang).to_lowercase() == header)
+    })
+}
+
+/// The explanation of the column with the i18n id `id`.
+fn explanation(id: &str) -> Option<&'static str> {
+    GLOSSARY
+        .iter()
+        .find(|(other, _)| *other == id)
+        .map(|(_, explanation)| *explanation)
+}
+
+/// Describes `columns` under every verbose table from now on. `all` picks every column.
+pub fn set_explained(columns: &[String]) -> Result<()> {
+    let mut explained = Vec::new();
+    for column in columns {
+        if column.eq_ignore_ascii_case("all") {
+            explained = GLOSSARY.iter().map(|(id, _)| *id).collect();
+            break;
+        }
+        let Some(id) = column_id(column) else {
+            anyhow::bail!("--explain: unknown column {column}, see `bwoverlay glossary`");
+        };
+        explained.push(id);
+    }
+    *EXPLAINED.write().unwrap() = explained;
+    Ok(())
+}
+
+/// One line per column picked with `set_explained`, empty when there are none.
+pub fn footer() -> String {
+    EXPLAINED
+        .read()
+        .unwrap()
+        .iter()
+        .filter_map(|id| Some(format!("{}: {}\n", text(id), explanation(id)?)))
+        .collect()
+}
+
+/// The whole glossary as a two column table.
+pub fn format_table() -> String {
+    let width = GLOSSARY
+        .iter()
+        .map(|(id, _)| text(id).chars().count())
+        .max()
+        .unwrap_or(0);
+    GLOSSARY
+        .iter()
+        .map(|(id, explanation)| format!("{:<width$}  {explanation}\n", text(id)))
+        .collect()
+}
+
+/// `bwoverlay glossary`.
+pub fn run() -> Result<()> {
+    print!("{}", format_table());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_column_is_explained_once() {
+        assert_eq!(
+            column_id("fkdr").and_then(explanation),
+            Some("Final Kills / Final Deaths (higher = more skilled)")
+        );
+        assert_eq!(column_id("KDR"), None);
+
+        for (id, _) in GLOSSARY {
+            let count = GLOSSARY.iter().filter(|(other, _)| other == id).count();
+            assert_eq!(count, 1, "{id} is listed {count} times");
+            assert_ne!(t(id, "en"), *id, "{id} has no header");
+        }
+        assert_eq!(format_table().lines().count(), GLOSSARY.len());
+    }
+
+    #[test]
+    fn headers_are_matched_in_either_language() {
+        // Headers from every language are recognized, whichever one the overlay runs in
+        assert_eq!(column_id("étoile"), Some("column.star"));
+        assert_eq!(column_id("KDR Arcade"), Some("column.arcade_kdr"));
+        assert_eq!(column_id("star"), Some("column.star"));
+    }
+}
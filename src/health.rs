@@ -0,0 +1,127 @@
+use anyhow::{bail, Context, Result};
+use log::{error, info};
+use std::{sync::Arc, time::Duration};
+use tokio::{task::JoinHandle, time::Instant};
+
+use crate::{api::HypixelApiClient, get_hypixel_data, get_player_uuids, notification, Uuid};
+
+/// Failed checks in a row before the user is notified, so one dropped request stays quiet.
+const FAILURES_BEFORE_NOTIFYING: u32 = 3;
+
+/// Looks up a well known player every `interval` through the same Mojang and Hypixel paths as
+/// lobby lookups, to notice a broken integration before a lobby comes back empty.
+pub struct HealthCheck {
+    player_name: String,
+    expected_uuid: Uuid,
+    interval: Duration,
+    error_count: u32,
+    consecutive_failures: u32,
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        HealthCheck {
+            player_name: "Notch".to_string(),
+            expected_uuid: "069a79f4-44e9-4726-a5be-fca90e38aaf5".to_string(),
+            interval: Duration::from_secs(10 * 60),
+            error_count: 0,
+            consecutive_failures: 0,
+        }
+    }
+}
+
+impl HealthCheck {
+    /// Runs the first check one `interval` after startup, then every `interval`. `client` should
+    /// be the one lookups use, so the checks count against the quota it tracks.
+    pub fn spawn(mut self, client: Arc<HypixelApiClient>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval_at(Instant::now() + self.interval, self.interval);
+
+            loop {
+                interval.tick().await;
+                match self.check(&client).await {
+                    Ok(()) => {
+                        info!("Health check passed");
+                        self.consecutive_failures = 0;
+                    }
+                    Err(reason) => self.record_failure(&reason),
+                }
+            }
+        })
+    }
+
+    async fn check(&self, client: &HypixelApiClient) -> Result<()> {
+        let (players, _) =
+            get_player_uuids(client.mojang(), vec![self.player_name.clone()]).await?;
+        let uuid = players
+            .into_keys()
+            .next()
+            .with_context(|| format!("Could not resolve the UUID of {}", self.player_name))?;
+        self.verify(uuid, client).await
+    }
+
+    /// Checks that `uuid`, as resolved by Mojang, is the expected player and that Hypixel knows it.
+    async fn verify(&self, uuid: Uuid, client: &HypixelApiClient) -> Result<()> {
+        // Mojang answers without dashes
+        if uuid.replace('-', "") != self.expected_uuid.replace('-', "") {
+            bail!(
+                "{} resolved to {uuid} instead of {}",
+                self.player_name,
+                self.expected_uuid
+            );
+        }
+
+        get_hypixel_data(uuid, client).await?;
+        Ok(())
+    }
+
+    fn record_failure(&mut self, reason: &anyhow::Error) {
+        self.error_count += 1;
+        self.consecutive_failures += 1;
+        error!(
+            "Health check failed: {reason:#} ({} failures this session)",
+            self.error_count
+        );
+
+        if self.consecutive_failures == FAILURES_BEFORE_NOTIFYING {
+            notification::send(
+                "bwoverlay health check failing",
+                &format!("The last {FAILURES_BEFORE_NOTIFYING} checks failed: {reason}"),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{api::ConnectionPool, test_helpers::MockHypixelServer};
+
+    #[tokio::test]
+    async fn checks_count_against_the_shared_quota() {
+        let server = MockHypixelServer::start().await;
+        server.register_player(
+            "069a79f4-44e9-4726-a5be-fca90e38aaf5",
+            "player_social_media",
+        );
+        let client = Arc::new(
+            HypixelApiClient::new(
+                "key".to_string(),
+                ConnectionPool::shared(reqwest::Client::new()),
+            )
+            .with_base_url(server.base_url()),
+        );
+        let mut check = HealthCheck::default();
+
+        // Mojang answers without dashes
+        let notch = "069a79f444e94726a5befca90e38aaf5".to_string();
+        check.verify(notch, &client).await.unwrap();
+        assert_eq!(client.quota_remaining(), 299);
+
+        let other = "00000000000000000000000000000000".to_string();
+        let reason = check.verify(other, &client).await.unwrap_err();
+        check.record_failure(&reason);
+        assert_eq!((check.error_count, check.consecutive_failures), (1, 1));
+    }
+}
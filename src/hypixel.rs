@@ -1,37 +1,298 @@
-#[allow(dead_code)]
-use serde_derive::Deserialize;
+use serde::Deserialize as _;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::RangeInclusive,
+    str::FromStr,
+};
 
-use crate::Uuid;
+use crate::{
+    arcade::{ApiArcadeStats, ArcadeStats},
+    color,
+    i18n::text,
+    Uuid,
+};
 
 pub const BASE: f32 = 10000.0;
 pub const GROWTH: f32 = 2500.0;
 
 const REVERSE_PQ_PREFIX: f32 = -(BASE - 0.5 * GROWTH) / GROWTH;
 const REVERSE_CONST: f32 = REVERSE_PQ_PREFIX * REVERSE_PQ_PREFIX;
-const GROWTH_DIVIDES_2: f32 = 2.0 / GROWTH;
 
-#[derive(Deserialize, Debug)]
+const SMURF_MAX_LEVEL: i32 = 100;
+const SMURF_MIN_FKDR: f32 = 3.0;
+/// More Bedwars quests completed than this marks a player who grinds dailies and weeklies.
+const GRINDER_MIN_QUESTS: i32 = 500;
+/// Above this star level an account with almost no karma was likely bought or boosted.
+const LOW_KARMA_MIN_LEVEL: i32 = 200;
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct HypixelPlayer {
     pub name: String,
     pub uuid: Uuid,
     pub rank: String,
     pub network_xp: i32,
     pub network_level: i32,
+    /// Network XP still needed for the next network level, -1 when the player's XP is unknown.
+    #[serde(default)]
+    pub network_xp_to_next_level: i32,
+    /// Network XP gained since the start of the current 100 level prestige, -1 when unknown.
+    #[serde(default)]
+    pub total_xp_this_prestige: i32,
+    /// How far through the current prestige the player is, from 0 to 100, -1 when unknown.
+    #[serde(default)]
+    pub prestige_completion_pct: f32,
     pub level: i32,
     pub winstreak: i32,
     pub fkdr: f32,
     pub wlr: f32,
+    /// FKDR and WLR over the player's last 100 games, the lifetime ratios when Hypixel has no
+    /// recent stats for them.
+    #[serde(default)]
+    pub recent_fkdr: f32,
+    #[serde(default)]
+    pub recent_wlr: f32,
     pub final_kills: i32,
     pub wins: i32,
     pub bed_break: i32,
+    #[serde(default)]
+    pub beds_lost: i32,
+    /// Beds broken per bed lost.
+    #[serde(default)]
+    pub bblr: f32,
+    /// How far the ratios can be trusted, from 0 to 1 by games played, reaching 1 at
+    /// `FULL_CONFIDENCE_GAMES`. -1 when the games played are unknown.
+    #[serde(default = "unknown_confidence")]
+    pub confidence: f32,
+    #[serde(skip)]
+    pub modes: HashMap<BedwarsMode, BedwarsModeStats>,
+    /// Set when the player is on the configured user's friends list.
+    #[serde(default)]
+    pub is_friend: bool,
+    /// Set when the player has a network booster queued or running.
+    #[serde(default)]
+    pub has_active_booster: bool,
+    /// Games played in the last 24 hours, -1 until fetched from the recent games API.
+    #[serde(default)]
+    pub games_last_24h: i32,
+    /// Epoch milliseconds of the player's first and most recent login, if Hypixel shares them.
+    #[serde(default)]
+    pub first_login: Option<i64>,
+    #[serde(default)]
+    pub last_login: Option<i64>,
+    /// Epoch milliseconds of the player's last logout. Hypixel only updates it on disconnect.
+    #[serde(default)]
+    pub last_logout: Option<i64>,
+    /// Set when the player links a YouTube, Twitch or Twitter account on Hypixel.
+    #[serde(default)]
+    pub is_content_creator: bool,
+    /// Hypixel karma, -1 when unknown.
+    #[serde(default)]
+    pub karma: i32,
+    /// Bedwars quests completed, -1 when unknown.
+    #[serde(default)]
+    pub quest_completions: i32,
+    #[serde(skip)]
+    pub arcade: ArcadeStats,
+    #[serde(skip)]
+    pub playstyle: PlayStyle,
 }
 
-impl HypixelPlayer {
-    pub fn from_api(raw_info: ApiHypixelPlayer, player_uuid: Uuid) -> Self {
+/// How a player likely plays, guessed from how their FKDR compares to their BBLR.
+///
+/// The serialized names are stored in cache files, so variants must not be renamed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub enum PlayStyle {
+    /// Plays defensively and picks off finals, dangerous to fight.
+    Sniper,
+    /// Goes straight for beds, dangerous to your bed.
+    Rusher,
+    Balanced,
+    /// FKDR or BBLR is unknown.
+    #[default]
+    Unknown,
+}
+
+impl PlayStyle {
+    pub fn classify(fkdr: f32, bblr: f32) -> Self {
+        // NaN ratios from zero deaths or beds lost fail every comparison
+        if !(fkdr >= 0.0 && bblr >= 0.0) {
+            PlayStyle::Unknown
+        } else if fkdr > 4.0 && bblr < 2.0 {
+            PlayStyle::Sniper
+        } else if bblr > 4.0 && fkdr < 3.0 {
+            PlayStyle::Rusher
+        } else {
+            PlayStyle::Balanced
+        }
+    }
+
+    /// The translated name shown in the `STYLE` column.
+    pub fn label(self) -> &'static str {
+        text(match self {
+            PlayStyle::Sniper => "style.sniper",
+            PlayStyle::Rusher => "style.rusher",
+            PlayStyle::Balanced => "style.balanced",
+            PlayStyle::Unknown => "style.unknown",
+        })
+    }
+}
+
+/// Serialized as `solo`, `doubles`, `threes` or `fours`, and deserialized through `FromStr` so
+/// configs can use any of its aliases. The serialized names are stored in cache files, so
+/// variants must not be renamed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BedwarsMode {
+    Solo,
+    Doubles,
+    Threes,
+    Fours,
+}
+
+impl BedwarsMode {
+    pub const ALL: [BedwarsMode; 4] = [
+        BedwarsMode::Solo,
+        BedwarsMode::Doubles,
+        BedwarsMode::Threes,
+        BedwarsMode::Fours,
+    ];
+
+    /// Prefix Hypixel uses for this mode's stat keys, e.g. `eight_one_wins_bedwars`.
+    fn api_prefix(self) -> &'static str {
+        match self {
+            BedwarsMode::Solo => "eight_one",
+            BedwarsMode::Doubles => "eight_two",
+            BedwarsMode::Threes => "four_three",
+            BedwarsMode::Fours => "four_four",
+        }
+    }
+
+    /// Parses the mode names used by `/locraw` (`BEDWARS_EIGHT_ONE`) and by chat (`Solo`).
+    pub fn from_chat(mode: &str) -> Option<Self> {
+        match mode {
+            "BEDWARS_EIGHT_ONE" | "Solo" => Some(BedwarsMode::Solo),
+            "BEDWARS_EIGHT_TWO" | "Doubles" => Some(BedwarsMode::Doubles),
+            "BEDWARS_FOUR_THREE" | "3v3v3v3" => Some(BedwarsMode::Threes),
+            "BEDWARS_FOUR_FOUR" | "4v4v4v4" => Some(BedwarsMode::Fours),
+            _ => None,
+        }
+    }
+
+    /// Fewest players a lobby of this mode starts with.
+    pub fn min_players(self) -> usize {
+        match self {
+            BedwarsMode::Solo | BedwarsMode::Doubles => 8,
+            BedwarsMode::Threes => 12,
+            BedwarsMode::Fours => 16,
+        }
+    }
+
+    pub fn max_players(self) -> usize {
+        match self {
+            BedwarsMode::Solo | BedwarsMode::Doubles => 16,
+            BedwarsMode::Threes => 24,
+            BedwarsMode::Fours => 32,
+        }
+    }
+
+    /// Player counts a `/who` in this mode can plausibly list.
+    pub fn player_count_range(self) -> RangeInclusive<usize> {
+        self.min_players()..=self.max_players()
+    }
+}
+
+/// Parses the names a user would type for a mode, ignoring case: `solo`, `solos` or `1`,
+/// `doubles`, `duos` or `2`, `threes`, `3` or `3v3v3v3`, and `fours`, `4` or `4v4v4v4`.
+impl FromStr for BedwarsMode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode.to_lowercase().as_str() {
+            "solo" | "solos" | "1" => Ok(BedwarsMode::Solo),
+            "doubles" | "duos" | "2" => Ok(BedwarsMode::Doubles),
+            "threes" | "3" | "3v3v3v3" => Ok(BedwarsMode::Threes),
+            "fours" | "4" | "4v4v4v4" => Ok(BedwarsMode::Fours),
+            _ => Err(format!(
+                "unknown Bedwars mode {mode:?}, expected solo, doubles, threes or fours"
+            )),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for BedwarsMode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mode = String::deserialize(deserializer)?;
+        mode.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Sanity checks a `/who` count against `mode`. Too few usually means `/who` ran before everyone
+/// loaded in, too many that the line was parsed wrong.
+pub fn validate_lobby_size(mode: BedwarsMode, player_count: usize) -> Option<String> {
+    let range = mode.player_count_range();
+    if player_count < *range.start() {
+        Some(format!(
+            "Lobby may be incomplete: only {player_count} players detected for {mode}"
+        ))
+    } else if player_count > *range.end() {
+        Some(format!(
+            "Lobby may be misparsed: {player_count} players detected for {mode}, which holds at most {}",
+            range.end()
+        ))
+    } else {
+        None
+    }
+}
+
+/// The mode names shown in game.
+impl fmt::Display for BedwarsMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BedwarsMode::Solo => "Solo",
+            BedwarsMode::Doubles => "Doubles",
+            BedwarsMode::Threes => "3v3v3v3",
+            BedwarsMode::Fours => "4v4v4v4",
+        })
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct BedwarsModeStats {
+    pub final_kills: i32,
+    pub final_deaths: i32,
+    pub wins: i32,
+    pub losses: i32,
+    pub fkdr: f32,
+    pub wlr: f32,
+    pub beds_broken: i32,
+    pub beds_lost: i32,
+    pub bblr: f32,
+}
+
+/// `numerator / denominator`, or -1 when either count is unknown, so `-1 / -1` isn't taken for
+/// a ratio of 1.
+fn known_ratio(numerator: i32, denominator: i32) -> f32 {
+    if numerator < 0 || denominator < 0 {
+        -1.0
+    } else {
+        numerator as f32 / denominator as f32
+    }
+}
+
+impl From<(ApiHypixelPlayer, Uuid)> for HypixelPlayer {
+    fn from((raw_info, player_uuid): (ApiHypixelPlayer, Uuid)) -> Self {
         let stats = raw_info.stats.as_ref();
         let bedwars = stats.and_then(|s| s.bedwars.as_ref());
         let achievements = raw_info.achievements.as_ref();
 
+        let (total_xp_this_prestige, prestige_completion_pct) =
+            prestige_progress(raw_info.network_xp.unwrap_or(0));
         let (final_kills, final_deaths) = (
             bedwars.and_then(|b| b.final_kills_bedwars).unwrap_or(-1),
             bedwars.and_then(|b| b.final_deaths_bedwars).unwrap_or(-1),
@@ -40,29 +301,405 @@ impl HypixelPlayer {
             bedwars.and_then(|b| b.wins_bedwars).unwrap_or(-1),
             bedwars.and_then(|b| b.losses_bedwars).unwrap_or(-1),
         );
+        let (beds_broken, beds_lost) = (
+            bedwars.and_then(|b| b.beds_broken_bedwars).unwrap_or(-1),
+            bedwars.and_then(|b| b.beds_lost_bedwars).unwrap_or(-1),
+        );
 
         HypixelPlayer {
             name: raw_info.name,
             uuid: player_uuid,
-            rank: match raw_info.monthly_package_rank.as_deref() {
-                Some("SUPERSTAR") => "MVP++".to_string(),
-                _ => raw_info
-                    .new_package_rank
-                    .as_deref()
-                    .unwrap_or("Default")
-                    .replace("_PLUS", "+"),
+            rank: match (
+                raw_info.rank.as_deref(),
+                raw_info.monthly_package_rank.as_deref(),
+            ) {
+                (Some("YOUTUBER"), _) => "YOUTUBE".to_string(),
+                (Some("GAME_MASTER"), _) => "GM".to_string(),
+                (Some("MODERATOR"), _) => "MOD".to_string(),
+                (Some(staff @ ("ADMIN" | "OWNER" | "HELPER")), _) => staff.to_string(),
+                (_, Some("SUPERSTAR")) => "MVP++".to_string(),
+                _ => match raw_info.new_package_rank.as_deref() {
+                    None | Some("NONE") => "Default".to_string(),
+                    Some(rank) => rank.replace("_PLUS", "+"),
+                },
             },
             network_xp: raw_info.network_xp.unwrap_or(0),
             network_level: calculate_level(raw_info.network_xp.unwrap_or(-1) as f32).round() as i32,
+            network_xp_to_next_level: xp_to_next_level(raw_info.network_xp.unwrap_or(0)),
+            total_xp_this_prestige,
+            prestige_completion_pct,
             level: achievements.and_then(|a| a.bedwars_level).unwrap_or(-1),
             winstreak: bedwars.and_then(|b| b.winstreak).unwrap_or(-1),
             fkdr: final_kills as f32 / final_deaths as f32,
             wlr: wins as f32 / losses as f32,
+            recent_fkdr: bedwars
+                .and_then(|b| {
+                    ApiBedwarsStats::recent_ratio(b.recent_final_kills, b.recent_final_deaths)
+                })
+                .unwrap_or(final_kills as f32 / final_deaths as f32),
+            recent_wlr: bedwars
+                .and_then(|b| ApiBedwarsStats::recent_ratio(b.recent_wins, b.recent_losses))
+                .unwrap_or(wins as f32 / losses as f32),
             final_kills,
             wins: bedwars.and_then(|b| b.wins_bedwars).unwrap_or(-1),
-            bed_break: bedwars.and_then(|b| b.beds_broken_bedwars).unwrap_or(-1),
+            bed_break: beds_broken,
+            beds_lost,
+            bblr: beds_broken as f32 / beds_lost as f32,
+            confidence: confidence(wins, losses),
+            modes: BedwarsMode::ALL
+                .into_iter()
+                .filter_map(|mode| Some((mode, bedwars?.mode_stats(mode)?)))
+                .collect(),
+            is_friend: false,
+            has_active_booster: false,
+            games_last_24h: -1,
+            first_login: raw_info.first_login,
+            last_login: raw_info.last_login,
+            last_logout: raw_info.last_logout,
+            is_content_creator: raw_info
+                .social_media
+                .as_ref()
+                .and_then(|social| social.links.as_ref())
+                .is_some_and(SocialMedia::any_linked),
+            karma: raw_info.karma.unwrap_or(-1),
+            quest_completions: raw_info
+                .quests
+                .map_or(-1, |quests| quests.bedwars_completions()),
+            arcade: stats.map(|s| s.arcade.totals()).unwrap_or_default(),
+            playstyle: PlayStyle::classify(
+                known_ratio(final_kills, final_deaths),
+                known_ratio(beds_broken, beds_lost),
+            ),
+        }
+    }
+}
+
+impl HypixelPlayer {
+    /// Stub for a player whose UUID or stats could not be fetched; every stat is unknown (`-1`).
+    pub fn unknown(name: &str) -> Self {
+        HypixelPlayer {
+            name: name.to_string(),
+            uuid: String::new(),
+            rank: "Default".to_string(),
+            network_xp: -1,
+            network_level: -1,
+            network_xp_to_next_level: -1,
+            total_xp_this_prestige: -1,
+            prestige_completion_pct: -1.0,
+            level: -1,
+            winstreak: -1,
+            fkdr: -1.0,
+            wlr: -1.0,
+            recent_fkdr: -1.0,
+            recent_wlr: -1.0,
+            final_kills: -1,
+            wins: -1,
+            bed_break: -1,
+            beds_lost: -1,
+            bblr: -1.0,
+            confidence: -1.0,
+            modes: HashMap::new(),
+            is_friend: false,
+            has_active_booster: false,
+            games_last_24h: -1,
+            first_login: None,
+            last_login: None,
+            last_logout: None,
+            is_content_creator: false,
+            karma: -1,
+            quest_completions: -1,
+            arcade: ArcadeStats::default(),
+            playstyle: PlayStyle::Unknown,
+        }
+    }
+
+    /// Updates a cached entry with a fresher lookup. Stats that `newer` doesn't know (`-1`) keep
+    /// their cached value, so a partial response during an outage doesn't wipe good data.
+    /// Login times always follow `newer`, and so do the name and rank when it came from Hypixel,
+    /// so name changes and rank upgrades show up.
+    pub fn merge(&mut self, newer: &HypixelPlayer) {
+        fn known<T: Copy + PartialEq + From<i8>>(current: &mut T, newer: T) {
+            if newer != T::from(-1) {
+                *current = newer;
+            }
+        }
+
+        // `HypixelPlayer::unknown` has no UUID and a placeholder rank
+        if !newer.uuid.is_empty() {
+            self.name.clone_from(&newer.name);
+            self.rank.clone_from(&newer.rank);
+        }
+        known(&mut self.network_xp, newer.network_xp);
+        known(&mut self.network_level, newer.network_level);
+        known(
+            &mut self.network_xp_to_next_level,
+            newer.network_xp_to_next_level,
+        );
+        known(
+            &mut self.total_xp_this_prestige,
+            newer.total_xp_this_prestige,
+        );
+        known(
+            &mut self.prestige_completion_pct,
+            newer.prestige_completion_pct,
+        );
+        known(&mut self.level, newer.level);
+        known(&mut self.winstreak, newer.winstreak);
+        known(&mut self.fkdr, newer.fkdr);
+        known(&mut self.wlr, newer.wlr);
+        known(&mut self.recent_fkdr, newer.recent_fkdr);
+        known(&mut self.recent_wlr, newer.recent_wlr);
+        known(&mut self.final_kills, newer.final_kills);
+        known(&mut self.wins, newer.wins);
+        known(&mut self.bed_break, newer.bed_break);
+        known(&mut self.beds_lost, newer.beds_lost);
+        known(&mut self.bblr, newer.bblr);
+        known(&mut self.confidence, newer.confidence);
+        known(&mut self.games_last_24h, newer.games_last_24h);
+        known(&mut self.karma, newer.karma);
+        known(&mut self.quest_completions, newer.quest_completions);
+        if !newer.modes.is_empty() {
+            self.modes = newer.modes.clone();
+        }
+        if newer.arcade != ArcadeStats::default() {
+            self.arcade = newer.arcade;
+        }
+        if newer.playstyle != PlayStyle::Unknown {
+            self.playstyle = newer.playstyle;
+        }
+        self.is_friend = newer.is_friend;
+        self.has_active_booster = newer.has_active_booster;
+        self.first_login = newer.first_login;
+        self.last_login = newer.last_login;
+        self.last_logout = newer.last_logout;
+    }
+
+    /// A copy safe to share with others: the name and UUID are replaced by the first 8 hex
+    /// characters of the UUID's SHA-256, so the same player always gets the same pseudonym.
+    /// Every stat is kept.
+    pub fn redact(&self) -> HypixelPlayer {
+        // Nicks have no UUID, their name is the only stable thing to hash
+        let identity = match self.identity() {
+            ("", name) => name,
+            (uuid, _) => uuid.to_string(),
+        };
+        let digest = ring::digest::digest(&ring::digest::SHA256, identity.as_bytes());
+        let hash: String = digest.as_ref()[..4]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+
+        HypixelPlayer {
+            name: format!("PLAYER_{hash}"),
+            uuid: hash,
+            ..self.clone()
+        }
+    }
+
+    /// A login after the last logout means the player hasn't disconnected since, so they are
+    /// online right now. Unknown when Hypixel hides either time.
+    pub fn is_currently_online(&self) -> bool {
+        match (self.last_login, self.last_logout) {
+            (Some(login), Some(logout)) => login > logout,
+            _ => false,
+        }
+    }
+
+    /// Whether the player completes Bedwars quests in bulk, and so plays for wins and finals
+    /// rather than for fun.
+    pub fn is_grinder(&self) -> bool {
+        self.quest_completions > GRINDER_MIN_QUESTS
+    }
+
+    /// The player's name prefixed with their rank bracket, e.g. `[MVP+] Steve`.
+    /// Players without a rank get their bare name.
+    pub fn display_name(&self) -> String {
+        match self.rank.as_str() {
+            "Default" => self.name.clone(),
+            rank => format!("[{}] {}", rank, self.name),
+        }
+    }
+
+    /// Names Mojang could not resolve to a UUID are almost always nicks.
+    pub fn is_nicked(&self) -> bool {
+        self.uuid.is_empty()
+    }
+
+    pub fn level_display(&self) -> StarDisplay {
+        StarDisplay {
+            level: self.level,
+            color_enabled: color::enabled(),
         }
     }
+
+    /// FKDR in `mode` when known and played, otherwise the lifetime aggregate.
+    pub fn fkdr_in(&self, mode: Option<BedwarsMode>) -> f32 {
+        mode.and_then(|mode| self.modes.get(&mode))
+            .map_or(self.fkdr, |stats| stats.fkdr)
+    }
+
+    /// WLR in `mode` when known and played, otherwise the lifetime aggregate.
+    pub fn wlr_in(&self, mode: Option<BedwarsMode>) -> f32 {
+        mode.and_then(|mode| self.modes.get(&mode))
+            .map_or(self.wlr, |stats| stats.wlr)
+    }
+
+    /// Beds broken in `mode` when known and played, otherwise the lifetime aggregate.
+    pub fn beds_broken_in(&self, mode: Option<BedwarsMode>) -> i32 {
+        mode.and_then(|mode| self.modes.get(&mode))
+            .map_or(self.bed_break, |stats| stats.beds_broken)
+    }
+
+    /// BBLR in `mode` when known and played, otherwise the lifetime aggregate.
+    pub fn bblr_in(&self, mode: Option<BedwarsMode>) -> f32 {
+        mode.and_then(|mode| self.modes.get(&mode))
+            .map_or(self.bblr, |stats| stats.bblr)
+    }
+
+    /// Confidence in the ratios of `mode` when known and played, otherwise the lifetime
+    /// confidence.
+    pub fn confidence_in(&self, mode: Option<BedwarsMode>) -> f32 {
+        mode.and_then(|mode| self.modes.get(&mode))
+            .map_or(self.confidence, |stats| {
+                confidence(stats.wins, stats.losses)
+            })
+    }
+
+    /// Whether the ratios of `mode` come from enough games to act on: a confidence of at least
+    /// `threshold`, or an unknown one.
+    pub fn ratios_trusted(&self, mode: Option<BedwarsMode>, threshold: f32) -> bool {
+        let confidence = self.confidence_in(mode);
+        confidence < 0.0 || confidence >= threshold
+    }
+
+    /// Either a low level account playing far too well, or a high level account with less karma
+    /// than `karma_threshold`, which real veterans pass many times over. An FKDR below
+    /// `confidence_threshold` is too unreliable to call a smurf on.
+    pub fn is_smurf_candidate(&self, karma_threshold: i32, confidence_threshold: f32) -> bool {
+        let low_level_high_fkdr = (0..SMURF_MAX_LEVEL).contains(&self.level)
+            && self.fkdr >= SMURF_MIN_FKDR
+            && self.ratios_trusted(None, confidence_threshold);
+        let low_karma_high_level =
+            self.level > LOW_KARMA_MIN_LEVEL && (0..karma_threshold).contains(&self.karma);
+
+        low_level_high_fkdr || low_karma_high_level
+    }
+
+    /// The usual Bedwars threat index: star level times FKDR squared.
+    pub fn index_in(&self, mode: Option<BedwarsMode>) -> f32 {
+        self.level as f32 * self.fkdr_in(mode).powi(2)
+    }
+}
+
+impl HypixelPlayer {
+    /// What identifies the player: their UUID, or their lowercased name while it is unresolved.
+    pub(crate) fn identity(&self) -> (&str, String) {
+        if self.uuid.is_empty() {
+            ("", self.name.to_lowercase())
+        } else {
+            (&self.uuid, String::new())
+        }
+    }
+}
+
+/// Two lookups of the same player are equal however much their stats differ.
+impl PartialEq for HypixelPlayer {
+    fn eq(&self, other: &Self) -> bool {
+        self.identity() == other.identity()
+    }
+}
+
+impl Eq for HypixelPlayer {}
+
+impl Hash for HypixelPlayer {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.identity().hash(state);
+    }
+}
+
+/// Sorts a lobby most threatening first, by threat index, into its display order. Players whose
+/// ratios aren't trusted at `confidence_threshold` count as no threat. Ties are ordered by
+/// identity, so the order is the same however the lobby came in.
+pub fn sort_by_threat(players: &mut [HypixelPlayer], confidence_threshold: f32) {
+    let threat = |player: &HypixelPlayer| {
+        if player.ratios_trusted(None, confidence_threshold) {
+            player.index_in(None)
+        } else {
+            0.0
+        }
+    };
+    players.sort_by(|a, b| {
+        threat(b)
+            .total_cmp(&threat(a))
+            .then_with(|| a.identity().cmp(&b.identity()))
+    });
+}
+
+impl TryFrom<Value> for HypixelPlayer {
+    type Error = ParseError;
+
+    /// Parses a raw `/player` response body, taking the UUID from the player object itself.
+    fn try_from(value: Value) -> Result<Self, ParseError> {
+        let data = ApiHypixelData::deserialize(&value).map_err(|e| (e, value.to_string()))?;
+        let raw_info = data.player.ok_or(ParseError::MissingField("player"))?;
+        let player_uuid = raw_info
+            .uuid
+            .clone()
+            .ok_or(ParseError::MissingField("player.uuid"))?;
+
+        Ok((raw_info, player_uuid).into())
+    }
+}
+
+const BODY_SNIPPET_LEN: usize = 200;
+
+#[derive(Debug)]
+pub enum ParseError {
+    JsonParse {
+        inner: serde_json::Error,
+        body: String,
+    },
+    MissingField(&'static str),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::JsonParse { inner, body } => write!(
+                f,
+                "Failed to parse Hypixel API response: {inner} (body: {})",
+                body_snippet(body)
+            ),
+            ParseError::MissingField(field) => {
+                write!(f, "Hypixel API response is missing `{field}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::JsonParse { inner, .. } => Some(inner),
+            ParseError::MissingField(_) => None,
+        }
+    }
+}
+
+/// The start of a response body, short enough for an error message.
+pub fn body_snippet(body: &str) -> String {
+    let snippet: String = body.chars().take(BODY_SNIPPET_LEN).collect();
+    if body.chars().count() > BODY_SNIPPET_LEN {
+        format!("{snippet}...")
+    } else {
+        snippet
+    }
+}
+
+impl From<(serde_json::Error, String)> for ParseError {
+    fn from((inner, body): (serde_json::Error, String)) -> Self {
+        ParseError::JsonParse { inner, body }
+    }
 }
 
 #[derive(Deserialize)]
@@ -74,16 +711,74 @@ pub struct ApiHypixelData {
 pub struct ApiHypixelPlayer {
     #[serde(rename = "displayname")]
     name: String,
+    uuid: Option<Uuid>,
+    rank: Option<String>,
     #[serde(rename = "monthlyPackageRank")]
     monthly_package_rank: Option<String>,
     #[serde(rename = "newPackageRank")]
     new_package_rank: Option<String>,
     #[serde(rename = "networkExp")]
     network_xp: Option<i32>,
+    #[serde(rename = "firstLogin")]
+    first_login: Option<i64>,
+    #[serde(rename = "lastLogin")]
+    last_login: Option<i64>,
+    #[serde(rename = "lastLogout")]
+    last_logout: Option<i64>,
+    #[serde(rename = "socialMedia")]
+    social_media: Option<ApiSocialMedia>,
+    karma: Option<i32>,
+    quests: Option<ApiQuests>,
     achievements: Option<ApiAchievements>,
     stats: Option<ApiStats>,
 }
 
+/// Every quest the player has started, keyed by quest id like `bedwars_daily_win`.
+#[derive(Deserialize, Clone)]
+struct ApiQuests(HashMap<String, ApiQuest>);
+
+impl ApiQuests {
+    /// Completions summed over every Bedwars quest.
+    fn bedwars_completions(&self) -> i32 {
+        self.0
+            .iter()
+            .filter(|(id, _)| id.starts_with("bedwars_"))
+            .map(|(_, quest)| quest.completions.len() as i32)
+            .sum()
+    }
+}
+
+#[derive(Deserialize, Clone)]
+struct ApiQuest {
+    /// One entry per completion, only the count matters.
+    #[serde(default)]
+    completions: Vec<serde::de::IgnoredAny>,
+}
+
+#[derive(Deserialize, Clone)]
+struct ApiSocialMedia {
+    links: Option<SocialMedia>,
+}
+
+/// The accounts listed under `player.socialMedia.links`.
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct SocialMedia {
+    #[serde(rename = "YOUTUBE")]
+    pub youtube: Option<String>,
+    #[serde(rename = "TWITCH")]
+    pub twitch: Option<String>,
+    #[serde(rename = "TWITTER")]
+    pub twitter: Option<String>,
+}
+
+impl SocialMedia {
+    fn any_linked(&self) -> bool {
+        [&self.youtube, &self.twitch, &self.twitter]
+            .into_iter()
+            .any(|link| link.as_deref().is_some_and(|link| !link.is_empty()))
+    }
+}
+
 #[derive(Deserialize, Clone)]
 struct ApiAchievements {
     bedwars_level: Option<i32>,
@@ -93,6 +788,8 @@ struct ApiAchievements {
 struct ApiStats {
     #[serde(rename = "Bedwars")]
     bedwars: Option<ApiBedwarsStats>,
+    #[serde(flatten)]
+    arcade: ApiArcadeStats,
 }
 
 #[derive(Deserialize, Clone)]
@@ -103,12 +800,795 @@ struct ApiBedwarsStats {
     wins_bedwars: Option<i32>,
     losses_bedwars: Option<i32>,
     beds_broken_bedwars: Option<i32>,
+    beds_lost_bedwars: Option<i32>,
+    /// Stats over the last 100 games, only present for some players.
+    #[serde(rename = "final_kills_bedwars_last_100")]
+    recent_final_kills: Option<i32>,
+    #[serde(rename = "final_deaths_bedwars_last_100")]
+    recent_final_deaths: Option<i32>,
+    #[serde(rename = "wins_bedwars_last_100")]
+    recent_wins: Option<i32>,
+    #[serde(rename = "losses_bedwars_last_100")]
+    recent_losses: Option<i32>,
+    /// Every other Bedwars stat, including the per-mode `<mode>_<stat>_bedwars` keys.
+    #[serde(flatten)]
+    per_mode: HashMap<String, Value>,
+}
+
+impl ApiBedwarsStats {
+    /// `numerator / denominator` over the last 100 games, `None` unless Hypixel has both.
+    fn recent_ratio(numerator: Option<i32>, denominator: Option<i32>) -> Option<f32> {
+        Some(numerator? as f32 / denominator? as f32)
+    }
+
+    fn mode_stat(&self, mode: BedwarsMode, stat: &str) -> Option<i32> {
+        self.per_mode
+            .get(&format!("{}_{stat}_bedwars", mode.api_prefix()))
+            .and_then(Value::as_i64)
+            .map(|value| value as i32)
+    }
+
+    /// `None` when the player has no recorded games in `mode`.
+    fn mode_stats(&self, mode: BedwarsMode) -> Option<BedwarsModeStats> {
+        let games_played = self.mode_stat(mode, "games_played")?;
+        if games_played <= 0 {
+            return None;
+        }
+
+        let final_kills = self.mode_stat(mode, "final_kills").unwrap_or(0);
+        let final_deaths = self.mode_stat(mode, "final_deaths").unwrap_or(0);
+        let wins = self.mode_stat(mode, "wins").unwrap_or(0);
+        let losses = self.mode_stat(mode, "losses").unwrap_or(0);
+        let beds_broken = self.mode_stat(mode, "beds_broken").unwrap_or(0);
+        let beds_lost = self.mode_stat(mode, "beds_lost").unwrap_or(0);
+
+        Some(BedwarsModeStats {
+            final_kills,
+            final_deaths,
+            wins,
+            losses,
+            fkdr: final_kills as f32 / final_deaths as f32,
+            wlr: wins as f32 / losses as f32,
+            beds_broken,
+            beds_lost,
+            bblr: beds_broken as f32 / beds_lost as f32,
+        })
+    }
+}
+
+/// ANSI color of the Bedwars star bracket for `level`, following the in-game prestige colors.
+/// Prestiges from 1000 upwards are rainbow in game; this returns the first color of the cycle.
+pub fn prestige_color(level: i32) -> &'static str {
+    match level {
+        ..100 => color::GRAY,
+        100..200 => color::WHITE,
+        200..300 => color::GOLD,
+        300..400 => color::AQUA,
+        400..500 => color::DARK_GREEN,
+        500..600 => color::DARK_AQUA,
+        600..700 => color::DARK_RED,
+        700..800 => color::LIGHT_PURPLE,
+        800..900 => color::BLUE,
+        900..1000 => color::DARK_PURPLE,
+        1000.. => color::RED,
+    }
+}
+
+const RAINBOW: [&str; 6] = [
+    color::RED,
+    color::GOLD,
+    color::YELLOW,
+    color::GREEN,
+    color::AQUA,
+    color::LIGHT_PURPLE,
+];
+
+/// A Bedwars star bracket such as `[342✫]`, colored by prestige when `color_enabled` is set.
+pub struct StarDisplay {
+    pub level: i32,
+    pub color_enabled: bool,
+}
+
+impl fmt::Display for StarDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let star = format!("[{}✫]", self.level);
+
+        if !self.color_enabled {
+            return write!(f, "{star}");
+        }
+
+        if self.level >= 1000 {
+            for (c, code) in star.chars().zip(RAINBOW.iter().cycle()) {
+                write!(f, "{code}{c}")?;
+            }
+            write!(f, "{}", color::RESET)
+        } else {
+            write!(f, "{}{star}{}", prestige_color(self.level), color::RESET)
+        }
+    }
 }
 
 fn calculate_level(exp: f32) -> f32 {
+    exact_level(exp).floor()
+}
+
+/// The network level including progress towards the next one, e.g. `2.5` halfway to level 3.
+/// Worked out in `f64`, as `f32` lands just under whole levels at high XP.
+fn exact_level(exp: f32) -> f32 {
     if exp < 0.0 {
         1.0
     } else {
-        (1.0 + REVERSE_PQ_PREFIX + (REVERSE_CONST + GROWTH_DIVIDES_2 * exp).sqrt()).floor()
+        let root = (REVERSE_CONST as f64 + 2.0 / GROWTH as f64 * exp as f64).sqrt();
+        (1.0 + REVERSE_PQ_PREFIX as f64 + root) as f32
+    }
+}
+
+/// Total network XP needed to reach `level`, the inverse of `exact_level`. Levels start at 1,
+/// so level 1 needs no XP and level 2 needs `BASE`.
+fn xp_for_level(level: f64) -> f64 {
+    let gained = level - 1.0;
+    GROWTH as f64 / 2.0 * gained * (gained - 1.0) + BASE as f64 * gained
+}
+
+/// Total network XP needed to reach the whole network level `level`, e.g. for prestige
+/// boundaries. The closed form of the arithmetic series each level adds `GROWTH` more XP to.
+/// Rounded up to the next `f32`, so the result always counts as `level` reached.
+pub fn calculate_prestige_xp(level: i32) -> f32 {
+    let xp = xp_for_level(level as f64);
+    let rounded = xp as f32;
+    if (rounded as f64) < xp {
+        rounded.next_up()
+    } else {
+        rounded
+    }
+}
+
+fn xp_to_next_level(network_xp: i32) -> i32 {
+    if network_xp <= 0 {
+        return -1;
+    }
+
+    let next_level = calculate_level(network_xp as f32) as i32 + 1;
+    (calculate_prestige_xp(next_level) - network_xp as f32).round() as i32
+}
+
+/// Games after which a player's ratios are fully trusted.
+const FULL_CONFIDENCE_GAMES: f32 = 500.0;
+
+/// Cached players from before confidence was tracked don't know it.
+fn unknown_confidence() -> f32 {
+    -1.0
+}
+
+/// Confidence in ratios over `wins + losses` games, -1 when either is unknown.
+fn confidence(wins: i32, losses: i32) -> f32 {
+    if wins < 0 || losses < 0 {
+        return -1.0;
+    }
+    ((wins + losses) as f32 / FULL_CONFIDENCE_GAMES).min(1.0)
+}
+
+/// Network levels per prestige.
+const PRESTIGE_LEVELS: i32 = 100;
+
+/// XP gained within the current prestige and the percentage of it completed, clamped to
+/// `0.0..=100.0`. Both are -1 for negative (unknown) XP. The first prestige starts at level 1,
+/// the lowest network level.
+fn prestige_progress(network_xp: i32) -> (i32, f32) {
+    if network_xp < 0 {
+        return (-1, -1.0);
+    }
+
+    let level = calculate_level(network_xp as f32) as i32;
+    let prestige_start = level - level % PRESTIGE_LEVELS;
+    let xp_at_start = calculate_prestige_xp(prestige_start.max(1));
+    let xp_at_next = calculate_prestige_xp(prestige_start + PRESTIGE_LEVELS);
+
+    let gained = network_xp as f32 - xp_at_start;
+    let pct = (gained / (xp_at_next - xp_at_start) * 100.0).clamp(0.0, 100.0);
+    (gained.round() as i32, pct)
+}
+
+/// Builds `HypixelPlayer` fixtures for tests, every stat left unset is unknown.
+#[cfg(test)]
+pub struct HypixelPlayerBuilder {
+    player: HypixelPlayer,
+}
+
+#[cfg(test)]
+impl HypixelPlayerBuilder {
+    pub fn new(name: &str, uuid: &str) -> Self {
+        HypixelPlayerBuilder {
+            player: HypixelPlayer {
+                uuid: uuid.to_string(),
+                fkdr: 0.0,
+                wlr: 0.0,
+                recent_fkdr: 0.0,
+                recent_wlr: 0.0,
+                bblr: 0.0,
+                ..HypixelPlayer::unknown(name)
+            },
+        }
+    }
+
+    pub fn rank(mut self, rank: &str) -> Self {
+        self.player.rank = rank.to_string();
+        self
+    }
+
+    pub fn level(mut self, level: i32) -> Self {
+        self.player.level = level;
+        self
+    }
+
+    pub fn fkdr(mut self, fkdr: f32) -> Self {
+        self.player.fkdr = fkdr;
+        self
+    }
+
+    pub fn network_xp(mut self, network_xp: i32) -> Self {
+        self.player.network_xp = network_xp;
+        self
+    }
+
+    pub fn final_kills(mut self, final_kills: i32) -> Self {
+        self.player.final_kills = final_kills;
+        self
+    }
+
+    pub fn wins(mut self, wins: i32) -> Self {
+        self.player.wins = wins;
+        self
+    }
+
+    pub fn winstreak(mut self, winstreak: i32) -> Self {
+        self.player.winstreak = winstreak;
+        self
+    }
+
+    pub fn quest_completions(mut self, quest_completions: i32) -> Self {
+        self.player.quest_completions = quest_completions;
+        self
+    }
+
+    pub fn karma(mut self, karma: i32) -> Self {
+        self.player.karma = karma;
+        self
+    }
+
+    pub fn first_login(mut self, first_login: i64) -> Self {
+        self.player.first_login = Some(first_login);
+        self
+    }
+
+    pub fn last_login(mut self, last_login: i64) -> Self {
+        self.player.last_login = Some(last_login);
+        self
+    }
+
+    pub fn last_logout(mut self, last_logout: i64) -> Self {
+        self.player.last_logout = Some(last_logout);
+        self
+    }
+
+    pub fn confidence(mut self, confidence: f32) -> Self {
+        self.player.confidence = confidence;
+        self
+    }
+
+    pub fn bblr(mut self, bblr: f32) -> Self {
+        self.player.bblr = bblr;
+        self
+    }
+
+    pub fn build(self) -> HypixelPlayer {
+        HypixelPlayer {
+            playstyle: PlayStyle::classify(self.player.fkdr, self.player.bblr),
+            ..self.player
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn xp_for_level_inverts_exact_level() {
+        for xp in [
+            0.0,
+            1.0,
+            9_999.0,
+            10_000.0,
+            123_456.0,
+            1_000_000.0,
+            5_000_000.0,
+        ] {
+            let round_trip = xp_for_level(exact_level(xp) as f64) as f32;
+            assert!(
+                (round_trip - xp).abs() <= 1.0,
+                "{xp} XP came back as {round_trip}"
+            );
+        }
+    }
+
+    #[test]
+    fn level_thresholds() {
+        assert_eq!(calculate_prestige_xp(1), 0.0);
+        assert_eq!(calculate_prestige_xp(2), BASE);
+        assert_eq!(calculate_prestige_xp(3), 2.0 * BASE + GROWTH);
+        assert_eq!(calculate_level(BASE), 2.0);
+    }
+
+    fn parse_fixture(body: &str) -> HypixelPlayer {
+        HypixelPlayer::try_from(serde_json::from_str::<Value>(body).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn social_media_links_mark_content_creators() {
+        let creator = parse_fixture(include_str!("../tests/fixtures/player_social_media.json"));
+        assert!(creator.is_content_creator);
+
+        let player = parse_fixture(include_str!(
+            "../tests/fixtures/player_no_social_media.json"
+        ));
+        assert!(!player.is_content_creator);
+    }
+
+    #[test]
+    fn arcade_stats_are_read_next_to_bedwars() {
+        let player = parse_fixture(include_str!("../tests/fixtures/player_social_media.json"));
+        assert_eq!(player.final_kills, 120);
+        assert_eq!(player.arcade.kdr(), Some(1.5));
+
+        let player = parse_fixture(include_str!(
+            "../tests/fixtures/player_no_social_media.json"
+        ));
+        assert_eq!(player.arcade.kdr(), None);
+    }
+
+    #[test]
+    fn recent_ratios_fall_back_to_lifetime() {
+        let player = parse_fixture(include_str!("../tests/fixtures/player_social_media.json"));
+        assert_eq!((player.fkdr, player.recent_fkdr), (3.0, 5.0));
+        assert_eq!((player.wlr, player.recent_wlr), (1.5, 1.5));
+
+        let player = parse_fixture(include_str!(
+            "../tests/fixtures/player_no_social_media.json"
+        ));
+        assert_eq!(player.recent_fkdr, player.fkdr);
+        assert_eq!(player.recent_wlr, player.wlr);
+    }
+
+    #[test]
+    fn conversions_from_json_and_api_structs_agree() {
+        for body in [
+            include_str!("../tests/fixtures/player_social_media.json"),
+            include_str!("../tests/fixtures/player_no_social_media.json"),
+        ] {
+            let value: Value = serde_json::from_str(body).unwrap();
+            let raw_info = ApiHypixelData::deserialize(&value).unwrap().player.unwrap();
+            let uuid = raw_info.uuid.clone().unwrap();
+
+            let from_value = HypixelPlayer::try_from(value).unwrap();
+            let from_api = HypixelPlayer::from((raw_info, uuid));
+
+            assert_eq!(format!("{from_value:?}"), format!("{from_api:?}"));
+        }
+    }
+
+    #[test]
+    fn bedwars_modes_parse_from_every_alias() {
+        for (aliases, mode) in [
+            (["solo", "Solos", "1"].as_slice(), BedwarsMode::Solo),
+            (&["DOUBLES", "duos", "2"], BedwarsMode::Doubles),
+            (&["threes", "3", "3v3v3v3"], BedwarsMode::Threes),
+            (&["Fours", "4", "4V4V4V4"], BedwarsMode::Fours),
+        ] {
+            for alias in aliases {
+                assert_eq!(alias.parse::<BedwarsMode>(), Ok(mode), "{alias}");
+            }
+            // Display output parses back to the same mode
+            assert_eq!(mode.to_string().parse::<BedwarsMode>(), Ok(mode));
+        }
+        assert!("squads".parse::<BedwarsMode>().is_err());
+        assert!("".parse::<BedwarsMode>().is_err());
+
+        let mode: BedwarsMode = serde_json::from_str("\"duos\"").unwrap();
+        assert_eq!(mode, BedwarsMode::Doubles);
+        assert!(serde_json::from_str::<BedwarsMode>("\"squads\"").is_err());
+        assert_eq!(BedwarsMode::Threes.player_count_range(), 12..=24);
+    }
+
+    #[test]
+    fn modes_and_playstyles_round_trip_through_serde() {
+        for (mode, json) in
+            BedwarsMode::ALL
+                .into_iter()
+                .zip(["\"solo\"", "\"doubles\"", "\"threes\"", "\"fours\""])
+        {
+            assert_eq!(serde_json::to_string(&mode).unwrap(), json);
+            assert_eq!(serde_json::from_str::<BedwarsMode>(json).unwrap(), mode);
+        }
+
+        for (style, json) in [
+            (PlayStyle::Sniper, "\"Sniper\""),
+            (PlayStyle::Rusher, "\"Rusher\""),
+            (PlayStyle::Balanced, "\"Balanced\""),
+            (PlayStyle::Unknown, "\"Unknown\""),
+        ] {
+            assert_eq!(serde_json::to_string(&style).unwrap(), json);
+            assert_eq!(serde_json::from_str::<PlayStyle>(json).unwrap(), style);
+        }
+    }
+
+    #[test]
+    fn playstyle_boundaries() {
+        for (fkdr, bblr, style) in [
+            (4.01, 1.99, PlayStyle::Sniper),
+            (4.0, 1.0, PlayStyle::Balanced),
+            (6.0, 2.0, PlayStyle::Balanced),
+            (2.99, 4.01, PlayStyle::Rusher),
+            (3.0, 6.0, PlayStyle::Balanced),
+            (2.0, 4.0, PlayStyle::Balanced),
+            (-1.0, 5.0, PlayStyle::Unknown),
+            (5.0, f32::NAN, PlayStyle::Unknown),
+        ] {
+            assert_eq!(PlayStyle::classify(fkdr, bblr), style, "{fkdr} / {bblr}");
+        }
+
+        let rusher = HypixelPlayerBuilder::new("Steve", "uuid")
+            .fkdr(1.5)
+            .bblr(5.0)
+            .build();
+        assert_eq!(rusher.playstyle, PlayStyle::Rusher);
+    }
+
+    #[test]
+    fn players_without_bedwars_stats_have_an_unknown_playstyle() {
+        let mut value: Value = serde_json::from_str(include_str!(
+            "../tests/fixtures/player_no_social_media.json"
+        ))
+        .unwrap();
+        // Finals but no beds
+        let player = HypixelPlayer::try_from(value.clone()).unwrap();
+        assert_eq!(player.playstyle, PlayStyle::Unknown);
+
+        value["player"].as_object_mut().unwrap().remove("stats");
+        let player = HypixelPlayer::try_from(value).unwrap();
+        assert_eq!(player.playstyle, PlayStyle::Unknown);
+    }
+
+    #[test]
+    fn grinders_complete_more_than_500_quests() {
+        for (quest_completions, grinder) in [(0, false), (499, false), (500, false), (1000, true)] {
+            let player = HypixelPlayerBuilder::new("Steve", "uuid")
+                .quest_completions(quest_completions)
+                .build();
+            assert_eq!(player.is_grinder(), grinder, "{quest_completions}");
+        }
+    }
+
+    #[test]
+    fn confidence_scales_with_games_played() {
+        assert_eq!(confidence(5, 5), 0.02);
+        assert_eq!(confidence(150, 100), 0.5);
+        assert_eq!(confidence(8000, 2000), 1.0);
+        assert_eq!(confidence(10, -1), -1.0);
+
+        let mut player = HypixelPlayerBuilder::new("Steve", "uuid")
+            .confidence(1.0)
+            .build();
+        player.modes.insert(
+            BedwarsMode::Solo,
+            BedwarsModeStats {
+                wins: 5,
+                losses: 5,
+                ..Default::default()
+            },
+        );
+        assert_eq!(player.confidence_in(Some(BedwarsMode::Solo)), 0.02);
+        assert_eq!(player.confidence_in(Some(BedwarsMode::Fours)), 1.0);
+        assert!(!player.ratios_trusted(Some(BedwarsMode::Solo), 0.3));
+        assert!(player.ratios_trusted(None, 0.3));
+    }
+
+    #[test]
+    fn low_karma_high_level_is_smurf_candidate() {
+        let player = |level, karma| {
+            HypixelPlayerBuilder::new("Steve", "uuid")
+                .level(level)
+                .karma(karma)
+                .fkdr(1.0)
+                .build()
+        };
+
+        assert!(player(300, 500).is_smurf_candidate(10_000, 0.3));
+        assert!(!player(300, 5_000_000).is_smurf_candidate(10_000, 0.3));
+        assert!(!player(300, -1).is_smurf_candidate(10_000, 0.3));
+    }
+
+    #[test]
+    fn lobby_size_bounds() {
+        assert!(validate_lobby_size(BedwarsMode::Solo, 7).is_some());
+        assert_eq!(validate_lobby_size(BedwarsMode::Solo, 8), None);
+        assert_eq!(validate_lobby_size(BedwarsMode::Fours, 32), None);
+        assert!(validate_lobby_size(BedwarsMode::Fours, 33).is_some());
+    }
+
+    #[test]
+    fn merge_keeps_known_stats() {
+        let mut cached = HypixelPlayerBuilder::new("Steve", "uuid")
+            .level(250)
+            .fkdr(3.5)
+            .final_kills(1_200)
+            .winstreak(4)
+            .last_login(1)
+            .build();
+        // A failed lookup for FKDR, which stays unknown rather than 0.0
+        let newer = HypixelPlayerBuilder::new("Steve", "uuid")
+            .level(251)
+            .fkdr(-1.0)
+            .final_kills(1_210)
+            .last_login(2)
+            .build();
+
+        cached.merge(&newer);
+
+        assert_eq!(cached.level, 251);
+        assert_eq!(cached.final_kills, 1_210);
+        assert_eq!(cached.fkdr, 3.5);
+        assert_eq!(cached.winstreak, 4);
+        assert_eq!(cached.last_login, Some(2));
+        assert_eq!(cached.first_login, None);
+    }
+
+    #[test]
+    fn merge_follows_name_changes_and_rank_upgrades() {
+        let mut cached = HypixelPlayerBuilder::new("Steve", "uuid")
+            .rank("VIP")
+            .build();
+
+        cached.merge(&HypixelPlayer::unknown("Steve"));
+        assert_eq!(cached.rank, "VIP");
+
+        let newer = HypixelPlayerBuilder::new("Steve2", "uuid")
+            .rank("MVP+")
+            .build();
+        cached.merge(&newer);
+        assert_eq!(cached.name, "Steve2");
+        assert_eq!(cached.rank, "MVP+");
+    }
+
+    #[test]
+    fn online_when_last_login_is_after_last_logout() {
+        let online = HypixelPlayerBuilder::new("Steve", "uuid")
+            .last_login(2_000)
+            .last_logout(1_000)
+            .build();
+        let offline = HypixelPlayerBuilder::new("Steve", "uuid")
+            .last_login(1_000)
+            .last_logout(2_000)
+            .build();
+
+        assert!(online.is_currently_online());
+        assert!(!offline.is_currently_online());
+        assert!(!HypixelPlayer::unknown("Steve").is_currently_online());
+    }
+
+    #[test]
+    fn display_name_includes_rank() {
+        let ranked = HypixelPlayerBuilder::new("Steve", "uuid")
+            .rank("MVP+")
+            .build();
+        let unranked = HypixelPlayerBuilder::new("Alex", "uuid").build();
+
+        assert_eq!(ranked.display_name(), "[MVP+] Steve");
+        assert_eq!(unranked.display_name(), "Alex");
+    }
+
+    #[test]
+    fn prestige_colors_change_at_each_hundred() {
+        for (below, from, expected) in [
+            (color::GRAY, 100, color::WHITE),
+            (color::WHITE, 200, color::GOLD),
+            (color::GOLD, 300, color::AQUA),
+            (color::AQUA, 400, color::DARK_GREEN),
+            (color::DARK_GREEN, 500, color::DARK_AQUA),
+            (color::DARK_AQUA, 600, color::DARK_RED),
+            (color::DARK_RED, 700, color::LIGHT_PURPLE),
+            (color::LIGHT_PURPLE, 800, color::BLUE),
+            (color::BLUE, 900, color::DARK_PURPLE),
+            (color::DARK_PURPLE, 1000, color::RED),
+        ] {
+            assert_eq!(prestige_color(from - 1), below, "level {}", from - 1);
+            assert_eq!(prestige_color(from), expected, "level {from}");
+        }
+        assert_eq!(prestige_color(0), color::GRAY);
+        assert_eq!(prestige_color(-1), color::GRAY);
+    }
+
+    #[test]
+    fn stars_are_plain_without_color() {
+        let plain = StarDisplay {
+            level: 1_000,
+            color_enabled: false,
+        };
+        assert_eq!(plain.to_string(), "[1000✫]");
+
+        let colored = StarDisplay {
+            level: 342,
+            color_enabled: true,
+        };
+        assert_eq!(
+            colored.to_string(),
+            format!("{}[342✫]{}", color::AQUA, color::RESET)
+        );
+    }
+
+    #[test]
+    fn bedwars_quest_completions_are_summed() {
+        let player = HypixelPlayer::try_from(json!({ "player": {
+            "displayname": "Steve",
+            "uuid": "uuid",
+            "quests": {
+                "bedwars_daily_win": {
+                    "active": { "started": 1 },
+                    "completions": [{ "time": 1 }, { "time": 2 }]
+                },
+                "bedwars_weekly_final_killer": { "completions": [{ "time": 3 }] },
+                "bedwars_daily_one_more": { "active": { "started": 4 } },
+                "skywars_solo_win": { "completions": [{ "time": 5 }] }
+            }
+        }}))
+        .unwrap();
+        assert_eq!(player.quest_completions, 3);
+
+        let player =
+            HypixelPlayer::try_from(json!({ "player": { "displayname": "Alex", "uuid": "uuid" } }))
+                .unwrap();
+        assert_eq!(player.quest_completions, -1);
+    }
+
+    #[test]
+    fn parse_errors_include_the_body() {
+        let body = json!({ "player": { "displayname": 42, "uuid": "uuid" } });
+        let err = HypixelPlayer::try_from(body).unwrap_err();
+
+        assert!(matches!(err, ParseError::JsonParse { .. }));
+        let message = err.to_string();
+        assert!(
+            message.contains(r#"(body: {"player":{"displayname":42"#),
+            "{message}"
+        );
+
+        // Long bodies are cut short
+        let long = "x".repeat(BODY_SNIPPET_LEN * 2);
+        let err = ParseError::from((serde_json::from_str::<Value>("{").unwrap_err(), long));
+        assert!(err
+            .to_string()
+            .ends_with(&format!("(body: {}...)", "x".repeat(BODY_SNIPPET_LEN))));
+    }
+
+    #[test]
+    fn display_name_for_every_rank_hypixel_sends() {
+        for (rank_fields, expected) in [
+            (json!({}), "Steve"),
+            (json!({ "newPackageRank": "NONE" }), "Steve"),
+            (json!({ "newPackageRank": "VIP" }), "[VIP] Steve"),
+            (json!({ "newPackageRank": "VIP_PLUS" }), "[VIP+] Steve"),
+            (json!({ "newPackageRank": "MVP" }), "[MVP] Steve"),
+            (json!({ "newPackageRank": "MVP_PLUS" }), "[MVP+] Steve"),
+            (
+                json!({ "newPackageRank": "MVP_PLUS", "monthlyPackageRank": "SUPERSTAR" }),
+                "[MVP++] Steve",
+            ),
+            (
+                json!({ "rank": "YOUTUBER", "newPackageRank": "MVP_PLUS" }),
+                "[YOUTUBE] Steve",
+            ),
+            (json!({ "rank": "ADMIN" }), "[ADMIN] Steve"),
+            (json!({ "rank": "GAME_MASTER" }), "[GM] Steve"),
+            (json!({ "rank": "MODERATOR" }), "[MOD] Steve"),
+        ] {
+            let mut player = json!({ "displayname": "Steve", "uuid": "uuid" });
+            player
+                .as_object_mut()
+                .unwrap()
+                .extend(rank_fields.as_object().unwrap().clone());
+
+            let player = HypixelPlayer::try_from(json!({ "player": player })).unwrap();
+            assert_eq!(player.display_name(), expected);
+        }
+    }
+
+    #[test]
+    fn prestige_progress_within_and_across_prestiges() {
+        assert_eq!(prestige_progress(0), (0, 0.0));
+        assert_eq!(prestige_progress(-1), (-1, -1.0));
+
+        let (gained, pct) = prestige_progress(calculate_prestige_xp(50) as i32);
+        assert_eq!(gained, calculate_prestige_xp(50) as i32);
+        assert!(
+            (pct - calculate_prestige_xp(50) / calculate_prestige_xp(100) * 100.0).abs() < 0.01
+        );
+
+        let (_, before_boundary) = prestige_progress(calculate_prestige_xp(99) as i32);
+        assert!(before_boundary > 95.0 && before_boundary < 100.0);
+        let (gained, at_boundary) = prestige_progress(calculate_prestige_xp(100) as i32 + 1);
+        assert!(gained < 100);
+        assert!(at_boundary < 0.01);
+    }
+
+    #[test]
+    fn players_are_keyed_on_uuid() {
+        let lobby: std::collections::HashSet<HypixelPlayer> = [
+            HypixelPlayerBuilder::new("Steve", "uuid-1")
+                .level(100)
+                .build(),
+            HypixelPlayerBuilder::new("Steve", "uuid-1")
+                .level(101)
+                .build(),
+            HypixelPlayerBuilder::new("Alex", "uuid-2").build(),
+            HypixelPlayer::unknown("Nick"),
+            HypixelPlayer::unknown("nick"),
+            HypixelPlayer::unknown("Other"),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(lobby.len(), 4);
+    }
+
+    #[test]
+    fn sorting_puts_the_biggest_threat_first() {
+        let mut lobby = [
+            HypixelPlayerBuilder::new("Casual", "uuid-1")
+                .level(50)
+                .fkdr(0.5)
+                .build(),
+            HypixelPlayerBuilder::new("Sweat", "uuid-2")
+                .level(500)
+                .fkdr(8.0)
+                .build(),
+            HypixelPlayerBuilder::new("Decent", "uuid-3")
+                .level(200)
+                .fkdr(2.0)
+                .build(),
+        ];
+
+        sort_by_threat(&mut lobby, 0.3);
+
+        let names: Vec<_> = lobby.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Sweat", "Decent", "Casual"]);
+
+        // A 10 game outlier is no threat yet
+        lobby[0].confidence = 0.02;
+        sort_by_threat(&mut lobby, 0.3);
+        let names: Vec<_> = lobby.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Decent", "Casual", "Sweat"]);
+    }
+
+    #[test]
+    fn prestige_xp_inverts_calculate_level() {
+        for level in [1, 10, 50, 100, 250, 500] {
+            assert_eq!(
+                calculate_level(calculate_prestige_xp(level)),
+                level as f32,
+                "level {level}"
+            );
+        }
+        for level in 1..1000 {
+            assert!(calculate_prestige_xp(level + 1) > calculate_prestige_xp(level));
+        }
+    }
+
+    #[test]
+    fn xp_to_next_level_counts_remaining_xp() {
+        assert_eq!(xp_to_next_level(2_500), 7_500);
+        assert_eq!(xp_to_next_level(10_000), 12_500);
+        assert_eq!(xp_to_next_level(0), -1);
+        assert_eq!(xp_to_next_level(-5), -1);
     }
 }
@@ -0,0 +1,143 @@
+use std::{
+    collections::HashMap,
+    sync::{OnceLock, RwLock},
+};
+
+/// Languages the overlay's own output is available in.
+pub const LANGUAGES: [&str; 2] = ["en", "fr"];
+
+const EN: &[(&str, &str)] = &[
+    ("column.name", "NAME"),
+    ("column.star", "STAR"),
+    ("column.fkdr", "FKDR"),
+    ("column.rfkdr", "RFKDR"),
+    ("column.wlr", "WLR"),
+    ("column.ws", "WS"),
+    ("column.finals", "FINALS"),
+    ("column.beds", "BEDS"),
+    ("column.bblr", "BBLR"),
+    ("column.index", "INDEX"),
+    ("column.recent", "RECENT"),
+    ("column.karma", "KARMA"),
+    ("column.norm", "NORM"),
+    ("column.arcade_kdr", "ArcadeKDR"),
+    ("column.style", "STYLE"),
+    ("style.sniper", "Sniper"),
+    ("style.rusher", "Rusher"),
+    ("style.balanced", "Balanced"),
+    ("style.unknown", "-"),
+    ("label.xp_to_next", "XP to next"),
+    ("label.prestige", "Prestige"),
+    ("tag.friend", "[FRIEND]"),
+    ("tag.online", "[ONLINE]"),
+    ("team.header", "{team} team"),
+    ("color.red", "Red"),
+    ("color.blue", "Blue"),
+    ("color.green", "Green"),
+    ("color.yellow", "Yellow"),
+    ("color.aqua", "Aqua"),
+    ("color.white", "White"),
+    ("color.pink", "Pink"),
+    ("color.gray", "Gray"),
+    ("team.stars", "STARS"),
+    ("team.threat", "THREAT"),
+    ("alert.high_threat", "High threat player"),
+    ("alert.tripped", "{player} tripped {checks}"),
+];
+
+const FR: &[(&str, &str)] = &[
+    ("column.name", "NOM"),
+    ("column.star", "ÉTOILE"),
+    ("column.fkdr", "FKDR"),
+    ("column.rfkdr", "RFKDR"),
+    ("column.wlr", "WLR"),
+    ("column.ws", "WS"),
+    ("column.finals", "FINAUX"),
+    ("column.beds", "LITS"),
+    ("column.bblr", "BBLR"),
+    ("column.index", "INDICE"),
+    ("column.recent", "RÉCENT"),
+    ("column.karma", "KARMA"),
+    ("column.norm", "NORM"),
+    ("column.arcade_kdr", "KDR Arcade"),
+    ("column.style", "STYLE"),
+    ("style.sniper", "Sniper"),
+    ("style.rusher", "Rusher"),
+    ("style.balanced", "Équilibré"),
+    ("style.unknown", "-"),
+    ("label.xp_to_next", "XP restant"),
+    ("label.prestige", "Prestige"),
+    ("tag.friend", "[AMI]"),
+    ("tag.online", "[EN LIGNE]"),
+    ("team.header", "Équipe {team}"),
+    ("color.red", "Rouge"),
+    ("color.blue", "Bleue"),
+    ("color.green", "Verte"),
+    ("color.yellow", "Jaune"),
+    ("color.aqua", "Aqua"),
+    ("color.white", "Blanche"),
+    ("color.pink", "Rose"),
+    ("color.gray", "Grise"),
+    ("team.stars", "ÉTOILES"),
+    ("team.threat", "MENACE"),
+    ("alert.high_threat", "Joueur très dangereux"),
+    ("alert.tripped", "{player} a déclenché {checks}"),
+];
+
+static LANGUAGE: RwLock<&str> = RwLock::new("en");
+
+fn translations() -> &'static HashMap<&'static str, HashMap<&'static str, &'static str>> {
+    static TRANSLATIONS: OnceLock<HashMap<&str, HashMap<&str, &str>>> = OnceLock::new();
+    TRANSLATIONS.get_or_init(|| {
+        HashMap::from([
+            ("en", EN.iter().copied().collect()),
+            ("fr", FR.iter().copied().collect()),
+        ])
+    })
+}
+
+/// `key` in `lang`, falling back to English for unknown languages and untranslated keys.
+pub fn t(key: &'static str, lang: &str) -> &'static str {
+    let translations = translations();
+    translations
+        .get(lang)
+        .and_then(|strings| strings.get(key))
+        .or_else(|| translations["en"].get(key))
+        .copied()
+        .unwrap_or(key)
+}
+
+/// `key` in the language picked with `set_language`.
+pub fn text(key: &'static str) -> &'static str {
+    t(key, &LANGUAGE.read().unwrap())
+}
+
+/// Switches every `text` lookup afterwards to `lang`. Unknown languages are ignored.
+pub fn set_language(lang: &str) {
+    if let Some(&lang) = LANGUAGES.iter().find(|&&known| known == lang) {
+        *LANGUAGE.write().unwrap() = lang;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_language_has_every_key() {
+        let translations = translations();
+        for lang in LANGUAGES {
+            for (key, _) in EN {
+                assert!(
+                    translations[lang].contains_key(key),
+                    "{lang} is missing {key}"
+                );
+            }
+            assert_eq!(translations[lang].len(), EN.len(), "{lang} has extra keys");
+        }
+
+        assert_eq!(t("column.name", "fr"), "NOM");
+        assert_eq!(t("column.name", "xx"), "NAME");
+        assert_eq!(t("color.red", "fr"), "Rouge");
+    }
+}
@@ -0,0 +1,387 @@
+use log::{debug, warn};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    hypixel::{BedwarsMode, HypixelPlayer},
+    metrics::metrics,
+    team::TeamColor,
+    Uuid,
+};
+
+/// The players listed by the most recent `/who`.
+#[derive(Debug, Clone)]
+pub struct LobbySnapshot {
+    pub names: Vec<String>,
+    /// The log the `/who` was read from in multi-instance mode.
+    pub source_instance: Option<PathBuf>,
+    /// Stats of the lobby once every player has been looked up, empty until then.
+    pub players: Vec<HypixelPlayer>,
+    /// Ties together the log lines of every lookup for this lobby.
+    pub lobby_id: String,
+    /// The mode the lobby was detected as, if any.
+    pub mode: Option<BedwarsMode>,
+    /// The team of every player seen chatting in game, keyed by lowercased name. Empty before the
+    /// game starts.
+    pub teams: HashMap<String, TeamColor>,
+}
+
+impl LobbySnapshot {
+    /// The snapshot with every player redacted, for exports shared with others. The source log is
+    /// dropped too since its path usually holds the user's account name.
+    pub fn redacted(&self) -> LobbySnapshot {
+        let players: Vec<HypixelPlayer> = self.players.iter().map(HypixelPlayer::redact).collect();
+        LobbySnapshot {
+            names: players.iter().map(|player| player.name.clone()).collect(),
+            source_instance: None,
+            players,
+            lobby_id: self.lobby_id.clone(),
+            mode: self.mode,
+            // Keyed by the real names, which redacted players no longer have
+            teams: HashMap::new(),
+        }
+    }
+}
+
+/// Cached Bedwars level above which a player is looked up before the rest of the lobby.
+const HIGH_LEVEL: i32 = 300;
+
+/// How urgently a player's stats are fetched. Later variants are fetched first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LookupPriority {
+    Unknown,
+    /// Cached as above `HIGH_LEVEL`.
+    HighLevel,
+    /// Never looked up this session, so possibly a nick nothing is known about yet.
+    Nicked,
+}
+
+/// A resolved player waiting for their stats, ordered by priority in a `BinaryHeap`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PrioritizedLookup {
+    pub priority: LookupPriority,
+    pub name: String,
+    pub uuid: Uuid,
+}
+
+/// Cached stats are shown for this long before the player is looked up again.
+const PLAYER_CACHE_TTL: Duration = Duration::from_secs(30 * 60);
+/// Beyond this many players the least recently shown one is dropped.
+const PLAYER_CACHE_CAPACITY: usize = 1000;
+/// Share of the TTL after which an entry is due for a background refresh.
+const SOFT_EXPIRY: f64 = 0.9;
+/// Only players shown this recently are refreshed in the background, those from older lobbies
+/// are left to expire.
+const REFRESH_IF_SHOWN_WITHIN: Duration = Duration::from_secs(10 * 60);
+
+/// Why a player was dropped from the `PlayerCache`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionReason {
+    /// Cached for longer than the TTL.
+    Expired,
+    /// The least recently used entry of a full cache.
+    CapacityExceeded,
+}
+
+struct CachedPlayer {
+    player: HypixelPlayer,
+    fetched_at: Instant,
+    /// The cache's clock when the entry was last inserted or shown.
+    last_used: u64,
+    /// When the entry was last inserted or shown.
+    last_shown: Instant,
+    /// Set once a background refresh was started since `fetched_at`, so a failing one isn't
+    /// retried on every pass.
+    refresh_started: bool,
+}
+
+/// Stats fetched this session, keyed by lowercased player name so they can be shown again without
+/// another API call. Entries expire after a TTL and the least recently used one is evicted once
+/// the cache is full.
+pub struct PlayerCache {
+    players: HashMap<String, CachedPlayer>,
+    capacity: usize,
+    ttl: Duration,
+    /// Bumped on every use, orders entries from least to most recently used.
+    clock: u64,
+    /// Players evicted and not fetched again since.
+    evicted: HashSet<Uuid>,
+}
+
+impl Default for PlayerCache {
+    fn default() -> Self {
+        PlayerCache::new(PLAYER_CACHE_CAPACITY, PLAYER_CACHE_TTL)
+    }
+}
+
+impl PlayerCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        PlayerCache {
+            players: HashMap::new(),
+            capacity,
+            ttl,
+            clock: 0,
+            evicted: HashSet::new(),
+        }
+    }
+
+    /// Adds `player`, merging into any entry already cached under `name`. Suspicious responses
+    /// leave the cached entry as it was, see `should_replace`.
+    pub fn insert(&mut self, name: &str, player: HypixelPlayer) {
+        self.clock += 1;
+        match self.players.get_mut(&name.to_lowercase()) {
+            Some(cached) => {
+                if should_replace(&cached.player, &player) {
+                    cached.player.merge(&player);
+                    cached.fetched_at = Instant::now();
+                    cached.refresh_started = false;
+                }
+                cached.last_used = self.clock;
+                cached.last_shown = Instant::now();
+            }
+            None => {
+                self.players.insert(
+                    name.to_lowercase(),
+                    CachedPlayer {
+                        player,
+                        fetched_at: Instant::now(),
+                        last_used: self.clock,
+                        last_shown: Instant::now(),
+                        refresh_started: false,
+                    },
+                );
+                if self.players.len() > self.capacity {
+                    self.evict_least_recently_used();
+                }
+            }
+        }
+    }
+
+    /// Players missing a cached UUID are fetched first, then cached high level players.
+    pub fn lookup_priority(&mut self, name: &str) -> LookupPriority {
+        match self.get(name) {
+            Some(player) if player.uuid.is_empty() => LookupPriority::Nicked,
+            None => LookupPriority::Nicked,
+            Some(player) if player.level > HIGH_LEVEL => LookupPriority::HighLevel,
+            Some(_) => LookupPriority::Unknown,
+        }
+    }
+
+    /// Splits `names` into the cached stats of those already looked up and the names still
+    /// missing from the cache.
+    pub fn partition(&mut self, names: &[String]) -> (Vec<HypixelPlayer>, Vec<String>) {
+        let mut cached = Vec::new();
+        let mut missing = Vec::new();
+        for name in names {
+            match self.get(name) {
+                Some(player) => cached.push(player.clone()),
+                None => missing.push(name.clone()),
+            }
+        }
+        (cached, missing)
+    }
+
+    /// Updates the entry for `name` with a background refresh. Unlike `insert` it doesn't count as
+    /// showing the player, and players dropped from the cache meanwhile aren't added back.
+    pub fn refresh(&mut self, name: &str, player: HypixelPlayer) {
+        let Some(cached) = self.players.get_mut(&name.to_lowercase()) else {
+            return;
+        };
+        if should_replace(&cached.player, &player) {
+            cached.player.merge(&player);
+            cached.fetched_at = Instant::now();
+            cached.refresh_started = false;
+        }
+    }
+
+    /// The cache key and UUID of every entry in the last tenth of its TTL that was shown within
+    /// `REFRESH_IF_SHOWN_WITHIN`, to be fetched again before it expires. Each entry is handed out
+    /// once per fetch, so a failed refresh isn't retried. Players without a UUID can't be fetched
+    /// and are left to expire.
+    pub fn due_for_refresh(&mut self) -> Vec<(String, Uuid)> {
+        let soft_expiry = self.ttl.mul_f64(SOFT_EXPIRY);
+        self.players
+            .iter_mut()
+            .filter(|(_, cached)| {
+                let age = cached.fetched_at.elapsed();
+                age > soft_expiry && age <= self.ttl
+            })
+            .filter(|(_, cached)| {
+                !cached.refresh_started
+                    && !cached.player.uuid.is_empty()
+                    && cached.last_shown.elapsed() <= REFRESH_IF_SHOWN_WITHIN
+            })
+            .map(|(name, cached)| {
+                cached.refresh_started = true;
+                (name.clone(), cached.player.uuid.clone())
+            })
+            .collect()
+    }
+
+    /// Whether `uuid` was evicted since it was last fetched, forgetting it either way.
+    pub fn take_evicted(&mut self, uuid: &Uuid) -> bool {
+        self.evicted.remove(uuid)
+    }
+
+    /// The cached entry for `name` marked as used, evicting it instead once it has expired.
+    fn get(&mut self, name: &str) -> Option<&HypixelPlayer> {
+        let key = name.to_lowercase();
+        if self.players.get(&key)?.fetched_at.elapsed() > self.ttl {
+            let expired = self.players.remove(&key)?;
+            self.eviction_callback(&expired.player.uuid, EvictionReason::Expired);
+            return None;
+        }
+
+        self.clock += 1;
+        let cached = self.players.get_mut(&key)?;
+        cached.last_used = self.clock;
+        cached.last_shown = Instant::now();
+        Some(&cached.player)
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let Some(key) = self
+            .players
+            .iter()
+            .min_by_key(|(_, cached)| cached.last_used)
+            .map(|(key, _)| key.clone())
+        else {
+            return;
+        };
+        if let Some(evicted) = self.players.remove(&key) {
+            self.eviction_callback(&evicted.player.uuid, EvictionReason::CapacityExceeded);
+        }
+    }
+
+    fn eviction_callback(&mut self, uuid: &Uuid, reason: EvictionReason) {
+        debug!(uuid = uuid.as_str(); "Evicted {uuid} from the player cache: {reason:?}");
+        metrics().cache_eviction(reason);
+        self.evicted.insert(uuid.clone());
+    }
+}
+
+/// Lifetime stats only ever grow, so a fresh response with less network XP, final kills or wins
+/// than the cache points at a rollback or a stale API answer. Those are logged and kept out of
+/// the cache. A cached stat that was never known (`-1`) never blocks the update, and neither does
+/// a fresh one that is unknown, since `merge` keeps the cached value for it anyway.
+pub fn should_replace(cached: &HypixelPlayer, fresh: &HypixelPlayer) -> bool {
+    let mut replace = true;
+    for (stat, cached_value, fresh_value) in [
+        ("XP", cached.network_xp, fresh.network_xp),
+        ("final kills", cached.final_kills, fresh.final_kills),
+        ("wins", cached.wins, fresh.wins),
+    ] {
+        if cached_value != -1 && fresh_value != -1 && fresh_value < cached_value {
+            warn!(
+                "Suspicious {stat} decrease for {}: {cached_value} -> {fresh_value}",
+                fresh.name
+            );
+            replace = false;
+        }
+    }
+    replace
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hypixel::HypixelPlayerBuilder;
+    use std::collections::BinaryHeap;
+
+    #[test]
+    fn uncached_players_are_looked_up_first() {
+        let mut cache = PlayerCache::default();
+        for (name, level) in [("Veteran", 450), ("Casual", 40)] {
+            let player = HypixelPlayerBuilder::new(name, &format!("{name}-uuid"))
+                .level(level)
+                .build();
+            cache.insert(name, player);
+        }
+
+        let mut queue: BinaryHeap<PrioritizedLookup> = ["Casual", "Stranger", "Veteran"]
+            .into_iter()
+            .map(|name| PrioritizedLookup {
+                priority: cache.lookup_priority(name),
+                name: name.to_string(),
+                uuid: String::new(),
+            })
+            .collect();
+
+        let order: Vec<_> = std::iter::from_fn(|| queue.pop()).map(|l| l.name).collect();
+        assert_eq!(order, ["Stranger", "Veteran", "Casual"]);
+    }
+
+    #[test]
+    fn recently_shown_entries_close_to_expiring_are_due_for_refresh() {
+        let mut cache = PlayerCache::new(10, Duration::from_secs(100));
+        for (name, age, shown) in [
+            ("Fresh", 10, 10),
+            ("Stale", 95, 5),
+            ("Forgotten", 95, 20 * 60),
+            ("Expired", 150, 5),
+        ] {
+            cache.insert(name, HypixelPlayerBuilder::new(name, name).build());
+            let cached = cache.players.get_mut(&name.to_lowercase()).unwrap();
+            cached.fetched_at = Instant::now() - Duration::from_secs(age);
+            cached.last_shown = Instant::now() - Duration::from_secs(shown);
+        }
+
+        assert_eq!(
+            cache.due_for_refresh(),
+            [("stale".to_string(), "Stale".to_string())]
+        );
+        // A refresh that failed isn't handed out again
+        assert!(cache.due_for_refresh().is_empty());
+
+        cache.refresh("Stale", HypixelPlayerBuilder::new("Stale", "Stale").build());
+        cache.refresh("Gone", HypixelPlayerBuilder::new("Gone", "Gone").build());
+        assert!(cache.due_for_refresh().is_empty());
+        assert_eq!(cache.players.len(), 4);
+    }
+
+    #[test]
+    fn decreasing_lifetime_stats_are_not_cached() {
+        let player = |network_xp, final_kills, wins| {
+            HypixelPlayerBuilder::new("Steve", "uuid")
+                .network_xp(network_xp)
+                .final_kills(final_kills)
+                .wins(wins)
+                .build()
+        };
+        let cached = player(50_000, 200, 40);
+
+        assert!(should_replace(&cached, &player(50_000, 200, 40)));
+        assert!(should_replace(&cached, &player(51_000, 210, 41)));
+        assert!(!should_replace(&cached, &player(10_000, 200, 40)));
+        assert!(!should_replace(&cached, &player(50_000, 150, 40)));
+        assert!(!should_replace(&cached, &player(50_000, 200, 39)));
+        assert!(should_replace(&player(-1, -1, -1), &player(10, 1, 1)));
+        assert!(should_replace(&cached, &player(-1, -1, -1)));
+    }
+
+    #[test]
+    fn evicts_least_recently_used_and_expired_players() {
+        let player = |name: &str| HypixelPlayerBuilder::new(name, &format!("{name}-uuid")).build();
+        let mut cache = PlayerCache::new(2, Duration::from_secs(60));
+        cache.insert("Steve", player("Steve"));
+        cache.insert("Alex", player("Alex"));
+        // Showing Steve again makes Alex the least recently used
+        cache.partition(&["Steve".to_string()]);
+        cache.insert("Notch", player("Notch"));
+
+        let (cached, missing) = cache.partition(&["Steve".into(), "Alex".into(), "Notch".into()]);
+        assert_eq!(cached.len(), 2);
+        assert_eq!(missing, ["Alex"]);
+        assert!(cache.take_evicted(&"Alex-uuid".to_string()));
+        assert!(!cache.take_evicted(&"Alex-uuid".to_string()));
+
+        let mut expiring = PlayerCache::new(2, Duration::ZERO);
+        expiring.insert("Steve", player("Steve"));
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(expiring.partition(&["Steve".to_string()]).1, ["Steve"]);
+        assert!(expiring.take_evicted(&"Steve-uuid".to_string()));
+    }
+}
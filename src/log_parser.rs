@@ -0,0 +1,372 @@
+use log::{info, warn};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs::File,
+    hash::{Hash, Hasher},
+    io::{Read, Seek, SeekFrom},
+    path::PathBuf,
+};
+use tokio::sync::mpsc::{error::TrySendError, Sender};
+
+use crate::{hypixel::BedwarsMode, patterns::LogPatterns, server::Server, team::TeamColor};
+
+/// Something the overlay reacts to, parsed from a single chat line.
+#[derive(Debug)]
+pub struct LogEvent {
+    /// The log the line came from, only set in multi-instance mode.
+    pub source_instance: Option<PathBuf>,
+    pub kind: LogEventKind,
+}
+
+#[derive(Debug)]
+pub enum LogEventKind {
+    ModeDetected(BedwarsMode),
+    BedDestroyed {
+        team: String,
+        destroyer: String,
+    },
+    FinalKill(String),
+    /// A player's team, learned from their in-game chat.
+    TeamMember {
+        team: TeamColor,
+        player: String,
+    },
+    /// The names listed by `/who`.
+    Who(Vec<String>),
+    LobbyCountdown,
+    /// The client joined a server.
+    ServerConnected(Server),
+}
+
+/// Owns everything needed to read the log incrementally: where the previous read stopped, the
+/// last line seen and the patterns lines are matched against. Parsed events are sent to
+/// `event_tx` so the watcher thread never waits on lookups.
+pub struct MinecraftLogParser {
+    log_path: PathBuf,
+    last_offset: u64,
+    last_line_hash: u64,
+    patterns: LogPatterns,
+    event_tx: Sender<LogEvent>,
+    tag_events: bool,
+    /// The names of an `ONLINE:` list that wrapped, waiting for its continuation lines.
+    pending_online: Option<String>,
+}
+
+impl MinecraftLogParser {
+    /// Starts reading at the current end of the log so lines from before startup are ignored.
+    /// `tag_events` marks every event with `log_path`, for telling instances apart.
+    pub fn new(
+        log_path: PathBuf,
+        patterns: LogPatterns,
+        event_tx: Sender<LogEvent>,
+        tag_events: bool,
+    ) -> Self {
+        let last_offset = std::fs::metadata(&log_path)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+
+        MinecraftLogParser {
+            log_path,
+            last_offset,
+            last_line_hash: 0,
+            patterns,
+            event_tx,
+            tag_events,
+            pending_online: None,
+        }
+    }
+
+    /// Called whenever the log changed. Reads every complete line appended since the previous
+    /// call and sends the events they contain.
+    pub fn process_event(&mut self) {
+        let lines = match self.read_new_lines() {
+            Ok(lines) => lines,
+            Err(e) => {
+                warn!("Error reading log: {e}");
+                return;
+            }
+        };
+
+        for line in lines {
+            let hash = hash_line(&line);
+            if hash == self.last_line_hash {
+                continue;
+            }
+            self.last_line_hash = hash;
+
+            for kind in self.parse_line(&line) {
+                let event = LogEvent {
+                    source_instance: self.tag_events.then(|| self.log_path.clone()),
+                    kind,
+                };
+                match self.event_tx.try_send(event) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(event)) => warn!("Dropping log event {event:?}"),
+                    Err(TrySendError::Closed(_)) => return,
+                }
+            }
+        }
+    }
+
+    fn read_new_lines(&mut self) -> std::io::Result<Vec<String>> {
+        let mut file = File::open(&self.log_path)?;
+        let len = file.metadata()?.len();
+
+        // Minecraft starts a fresh latest.log on every launch
+        if len < self.last_offset {
+            info!("Log file was truncated, reading from the start");
+            self.last_offset = 0;
+        }
+
+        file.seek(SeekFrom::Start(self.last_offset))?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+
+        // Leave a partially written line for the next call
+        let Some(end) = buf.iter().rposition(|&b| b == b'\n') else {
+            return Ok(Vec::new());
+        };
+        self.last_offset += end as u64 + 1;
+
+        Ok(String::from_utf8_lossy(&buf[..end])
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Parses one line. Usually yields at most one event, but a line that ends a wrapped
+    /// `ONLINE:` list without continuing it also yields the finished list first.
+    fn parse_line(&mut self, line: &str) -> Vec<LogEventKind> {
+        let mut events = Vec::new();
+        if let Some(mut names) = self.pending_online.take() {
+            match self.patterns.detect_online_continuation(line) {
+                Some(more) => {
+                    if names.ends_with(',') {
+                        names.push(' ');
+                    }
+                    names.push_str(more);
+                    return self.online_list(names).into_iter().collect();
+                }
+                None => events.push(who(&names)),
+            }
+        }
+        events.extend(self.parse_single_line(line));
+        events
+    }
+
+    /// Sends a complete list on, or keeps a cut off one until the next line arrives. Continuation
+    /// lines are only looked for right after the list, so unrelated chat can't be mistaken for
+    /// one later on.
+    fn online_list(&mut self, names: String) -> Option<LogEventKind> {
+        if self.patterns.online_truncated(&names) {
+            self.pending_online = Some(names);
+            return None;
+        }
+        Some(who(&names))
+    }
+
+    fn parse_single_line(&mut self, line: &str) -> Option<LogEventKind> {
+        if let Some(mode) = self.patterns.detect_mode(line) {
+            return Some(LogEventKind::ModeDetected(mode));
+        }
+        if let Some((team, destroyer)) = self.patterns.detect_bed_destruction(line) {
+            return Some(LogEventKind::BedDestroyed {
+                team: team.to_string(),
+                destroyer: destroyer.to_string(),
+            });
+        }
+        if let Some(victim) = self.patterns.detect_final_kill(line) {
+            return Some(LogEventKind::FinalKill(victim.to_string()));
+        }
+        if let Some((team, player)) = self.patterns.detect_team_member(line) {
+            return Some(LogEventKind::TeamMember {
+                team,
+                player: player.to_string(),
+            });
+        }
+        if let Some(captures) = self.patterns.online.captures(line) {
+            let names = captures.get(1)?.as_str().to_string();
+            return self.online_list(names);
+        }
+        if self.patterns.detect_lobby_countdown(line) {
+            return Some(LogEventKind::LobbyCountdown);
+        }
+        if let Some(server) = self.patterns.detect_server(line) {
+            return Some(LogEventKind::ServerConnected(server));
+        }
+        None
+    }
+}
+
+fn who(names: &str) -> LogEventKind {
+    LogEventKind::Who(
+        names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+fn hash_line(line: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    line.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_helpers::ReplayLogFile, watcher};
+    use std::{sync::Arc, time::Duration};
+    use tokio::sync::mpsc::{self, Receiver};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn parser(replay: &ReplayLogFile) -> (MinecraftLogParser, Receiver<LogEvent>) {
+        let (event_tx, event_rx) = mpsc::channel(16);
+        let patterns =
+            LogPatterns::new(None, r"\[CHAT\] The game starts in \d+ seconds?!").unwrap();
+        let parser =
+            MinecraftLogParser::new(replay.path().to_path_buf(), patterns, event_tx, false);
+        (parser, event_rx)
+    }
+
+    #[tokio::test]
+    async fn who_response_from_a_growing_log() {
+        let replay = ReplayLogFile::new();
+        replay
+            .append_line("[12:00:00] [Client thread/INFO]: [CHAT] ONLINE: Old, Lobby")
+            .await;
+        let (parser, mut event_rx) = parser(&replay);
+        let parser = std::sync::Mutex::new(parser);
+        let poller = watcher::spawn_polling(
+            replay.path().display().to_string(),
+            Duration::from_millis(10),
+            Arc::new(move || parser.lock().unwrap().process_event()),
+        );
+
+        replay
+            .append_line("[12:00:01] [Client thread/INFO]: [CHAT] ONLINE: Steve, Alex")
+            .await;
+        let event = tokio::time::timeout(TIMEOUT, event_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        poller.abort();
+
+        match event.kind {
+            LogEventKind::Who(names) => assert_eq!(names, ["Steve", "Alex"]),
+            other => panic!("expected a /who response, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn rereads_a_truncated_log_from_the_start() {
+        let replay = ReplayLogFile::new();
+        replay
+            .append_line("[12:00:00] [Client thread/INFO]: [CHAT] ONLINE: Steve, Alex, Notch")
+            .await;
+        let (mut parser, mut event_rx) = parser(&replay);
+
+        replay.truncate().await;
+        replay
+            .append_line("[12:05:00] [Client thread/INFO]: [CHAT] ONLINE: Alex")
+            .await;
+        parser.process_event();
+
+        match event_rx.try_recv().unwrap().kind {
+            LogEventKind::Who(names) => assert_eq!(names, ["Alex"]),
+            other => panic!("expected a /who response, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn wrapped_who_response_is_joined() {
+        let replay = ReplayLogFile::new();
+        let (mut parser, mut event_rx) = parser(&replay);
+
+        for line in [
+            "[12:00:00] [Client thread/INFO]: [CHAT] ONLINE: Steve, Alex,",
+            "[12:00:00] [Client thread/INFO]: [CHAT] Notch, Jeb_",
+            // A cut off list followed by ordinary chat is sent as far as it got
+            "[12:00:05] [Client thread/INFO]: [CHAT] ONLINE: Steve, Alex, ",
+            "[12:00:06] [Client thread/INFO]: [CHAT] [MVP+] Steve: gl",
+        ] {
+            replay.append_line(line).await;
+        }
+        parser.process_event();
+
+        let mut names = Vec::new();
+        while let Ok(event) = event_rx.try_recv() {
+            if let LogEventKind::Who(who) = event.kind {
+                names.push(who);
+            }
+        }
+        assert_eq!(
+            names,
+            [
+                vec!["Steve", "Alex", "Notch", "Jeb_"],
+                vec!["Steve", "Alex"]
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn only_the_clients_own_connect_line_switches_servers() {
+        let replay = ReplayLogFile::new();
+        let (mut parser, mut event_rx) = parser(&replay);
+
+        for line in [
+            "[12:00:00] [Client thread/INFO]: [CHAT] Connecting to foo.net, 25565",
+            "[12:00:01] [Client thread/INFO]: [CHAT] [VIP] Alex: Connecting to foo.net, 25565",
+            "[12:00:02] [Render thread/INFO]: Connecting to eu.hypixel.net, 25565",
+        ] {
+            replay.append_line(line).await;
+        }
+        parser.process_event();
+
+        let mut servers = Vec::new();
+        while let Ok(event) = event_rx.try_recv() {
+            if let LogEventKind::ServerConnected(server) = event.kind {
+                servers.push(server);
+            }
+        }
+        assert_eq!(
+            servers,
+            [Server::Hypixel(crate::server::HypixelRegion::Europe)]
+        );
+    }
+
+    #[tokio::test]
+    async fn teams_from_in_game_chat() {
+        let replay = ReplayLogFile::new();
+        let (mut parser, mut event_rx) = parser(&replay);
+
+        for line in [
+            "[12:00:00] [Client thread/INFO]: [CHAT] [SHOUT] [RED] [MVP+] Steve: gl",
+            "[12:00:01] [Client thread/INFO]: [CHAT] [BLUE] Alex: rush mid",
+            // Ranks aren't teams
+            "[12:00:02] [Client thread/INFO]: [CHAT] [VIP] Notch: hi",
+        ] {
+            replay.append_line(line).await;
+        }
+        parser.process_event();
+
+        let mut teams = Vec::new();
+        while let Ok(event) = event_rx.try_recv() {
+            if let LogEventKind::TeamMember { team, player } = event.kind {
+                teams.push((team, player));
+            }
+        }
+        assert_eq!(
+            teams,
+            [
+                (TeamColor::Red, "Steve".to_string()),
+                (TeamColor::Blue, "Alex".to_string())
+            ]
+        );
+    }
+}
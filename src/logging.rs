@@ -0,0 +1,139 @@
+use anyhow::{Context, Result};
+use log::{
+    kv::{self, Key, Value, VisitSource},
+    LevelFilter, Log, Metadata, Record,
+};
+use serde_json::{Map, Value as JsonValue};
+use simple_logger::SimpleLogger;
+use std::{
+    future::Future,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+tokio::task_local! {
+    /// Set on every task working on one lobby, so their JSON log lines share a `lobby_id`.
+    static LOBBY_ID: String;
+}
+
+/// Logs through `simple_logger` until `set_json(true)` switches to one JSON object per line.
+/// The config is read after logging starts, so the format has to be switchable afterwards.
+/// Both formats go to stderr, leaving stdout to the stats output.
+struct OverlayLogger {
+    text: SimpleLogger,
+}
+
+impl Log for OverlayLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.text.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !JSON.load(Ordering::Relaxed) {
+            self.text.log(record);
+        } else if self.enabled(record.metadata()) {
+            eprintln!("{}", json_line(record));
+        }
+    }
+
+    fn flush(&self) {
+        self.text.flush();
+    }
+}
+
+/// Collects `info!(uuid = ...; "...")` style fields into the JSON object.
+struct JsonFields<'a>(&'a mut Map<String, JsonValue>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        self.0
+            .insert(key.to_string(), JsonValue::String(value.to_string()));
+        Ok(())
+    }
+}
+
+fn json_line(record: &Record) -> JsonValue {
+    let mut entry = Map::new();
+    let timestamp = OffsetDateTime::now_utc()
+        .format(&Rfc3339)
+        .unwrap_or_default();
+    entry.insert("timestamp".to_string(), timestamp.into());
+    entry.insert("level".to_string(), record.level().as_str().into());
+    entry.insert("target".to_string(), record.target().into());
+    entry.insert("message".to_string(), record.args().to_string().into());
+    if let Some(lobby_id) = current_lobby_id() {
+        entry.insert("lobby_id".to_string(), lobby_id.into());
+    }
+    let _ = record.key_values().visit(&mut JsonFields(&mut entry));
+
+    JsonValue::Object(entry)
+}
+
+pub fn init() -> Result<()> {
+    let text = SimpleLogger::new().env().with_level(LevelFilter::Info);
+    #[cfg(windows)]
+    simple_logger::set_up_windows_color_terminal();
+    log::set_max_level(text.max_level());
+    log::set_boxed_logger(Box::new(OverlayLogger { text })).context("Failed to start logging")
+}
+
+pub fn set_json(json: bool) {
+    JSON.store(json, Ordering::Relaxed);
+}
+
+/// A fresh id for the lobby of a `/who`.
+pub fn new_lobby_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// The lobby the current task is working on, if any.
+pub fn current_lobby_id() -> Option<String> {
+    LOBBY_ID.try_with(Clone::clone).ok()
+}
+
+/// Runs `future` as part of the lobby `lobby_id`.
+pub async fn in_lobby<F: Future>(lobby_id: String, future: F) -> F::Output {
+    LOBBY_ID.scope(lobby_id, future).await
+}
+
+/// Carries the current lobby into `future` before it is spawned, since task locals don't cross
+/// `tokio::spawn`.
+pub fn in_current_lobby<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let lobby_id = current_lobby_id();
+    async move {
+        match lobby_id {
+            Some(lobby_id) => in_lobby(lobby_id, future).await,
+            None => future.await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line() -> JsonValue {
+        json_line(
+            &Record::builder()
+                .args(format_args!("Fetched stats"))
+                .build(),
+        )
+    }
+
+    #[tokio::test]
+    async fn json_lines_carry_the_lobby_id_into_spawned_tasks() {
+        assert!(line().get("lobby_id").is_none());
+
+        let spawned = in_lobby("lobby-1".to_string(), async {
+            tokio::spawn(in_current_lobby(async { line() }))
+                .await
+                .unwrap()
+        })
+        .await;
+
+        assert_eq!(spawned["lobby_id"], "lobby-1");
+        assert_eq!(spawned["message"], "Fetched stats");
+    }
+}
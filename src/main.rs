@@ -1,268 +1,1119 @@
-use anyhow::Result;
-use hotwatch::{EventKind, Hotwatch};
-use hypixel::{ApiHypixelData, HypixelPlayer};
-use log::{error, info, warn, LevelFilter};
-use regex::Regex;
+use anyhow::{Context, Result};
+use api::{ConnectionPool, HypixelApiClient, PlayerLookupError};
+use boosters::BoosterList;
+use cli::Cli;
+use commands::CommandQueue;
+use config::{read_config, validate_config, Config, LogFormat, LoggerFormat, Thresholds};
+use export::StatsExporter;
+use formatter::Formatter;
+use friends::FriendsList;
+use game::{GameState, GlobalSessionStats, SessionStats};
+use health::HealthCheck;
+use hotwatch::Hotwatch;
+use hypixel::{body_snippet, validate_lobby_size, BedwarsMode, HypixelPlayer};
+use lobby::{LobbySnapshot, PlayerCache, PrioritizedLookup};
+use log::{debug, error, info, warn};
+use log_parser::{LogEventKind, MinecraftLogParser};
+use metrics::{metrics, Api};
+use normalizer::StatNormalizer;
+use notification::{AlertCooldown, PlayerAlert};
+use output::OutputSink;
+use patterns::LogPatterns;
+use profile::ProfileManager;
+use recent_games::RecentGamesCache;
 use reqwest::Client;
-use serde_derive::{Deserialize, Serialize};
+use serde_derive::Deserialize;
 use serde_json::json;
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use server::Server;
+use shutdown::ShutdownCoordinator;
+use stat_history::{StatHistory, HISTORY_MAX_RECORDS, HISTORY_PATH};
+use std::{
+    collections::{BinaryHeap, HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use team::TeamColor;
 use tokio::{
-    fs::{self, File},
-    io::AsyncWriteExt,
-    runtime::Runtime,
+    sync::{mpsc, Mutex},
+    task::{JoinHandle, JoinSet},
 };
 use uuid as uuid_crate;
+use watcher::{LogHandler, WatcherHealth};
 
 type Uuid = String;
 
+mod api;
+mod arcade;
+mod benchmark;
+mod boosters;
+mod cli;
+mod color;
+mod commands;
+mod compare;
+mod config;
+mod config_schema;
+mod display;
+mod events;
+mod export;
+mod formatter;
+mod friends;
+mod game;
+mod glossary;
+mod health;
 mod hypixel;
+mod i18n;
+mod lobby;
+mod log_parser;
+mod logging;
+mod metrics;
+mod minecraft_version;
+mod normalizer;
+mod notification;
+mod output;
+mod patterns;
+mod profile;
+mod recent_games;
+mod server;
+#[cfg(feature = "interactive")]
+mod setup;
+mod shutdown;
+mod stat_history;
+mod team;
+mod telemetry;
+#[cfg(test)]
+mod test_helpers;
+mod validate;
+mod watchdog;
+mod watcher;
+
+#[derive(Deserialize)]
+struct Player {
+    name: String,
+    id: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    logging::init()?;
+
+    let cli = Cli::parse()?;
+    glossary::set_explained(&cli.explain)?;
+    let base_config = read_config().await?;
+    let profiles = ProfileManager::new();
+    if let Some(command) = cli.profile_command {
+        return profiles.run_command(command, &base_config);
+    }
+    let config = Arc::new(match &cli.profile {
+        Some(name) => profiles.activate(name, &base_config)?,
+        None => base_config,
+    });
+    logging::set_json(config.logger_format == LoggerFormat::Json);
+    i18n::set_language(&config.language);
+    if cli.glossary {
+        return glossary::run();
+    }
+    if !cli.explain.is_empty() && config.log_format != LogFormat::Verbose {
+        warn!(
+            "--explain only adds to the verbose table, log-format is {:?}",
+            config.log_format
+        );
+    }
+
+    if let Some(command) = cli.config_command {
+        return config_schema::run_command(command, &config);
+    }
+
+    if cli.validate_config {
+        let passed = validate::run(&config).await;
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
+    if cli.benchmark_api {
+        return benchmark::run(&config).await;
+    }
+
+    if let Some(compare) = cli.compare_command {
+        return compare::run(&config, &compare.name_a, &compare.name_b, compare.markdown).await;
+    }
+
+    for problem in validate_config(&config) {
+        warn!("Config problem: {problem}");
+    }
+
+    let result = if cli.once {
+        run_once(Arc::clone(&config)).await
+    } else {
+        run(Arc::clone(&config)).await
+    };
+    if let (Err(e), Some(endpoint)) = (&result, &config.telemetry_endpoint) {
+        // Give the report a moment to go out before the runtime shuts down
+        let _ = tokio::time::timeout(TELEMETRY_FLUSH_TIMEOUT, telemetry::report(endpoint, e)).await;
+    }
+
+    result
+}
 
-#[derive(Deserialize, Serialize, Clone)]
-struct Config {
-    #[serde(rename = "log-path")]
-    log_path: String,
-    #[serde(rename = "api-key")]
-    api_key: String,
-    #[serde(rename = "quit-level")]
-    quit_level: i32,
+const TELEMETRY_FLUSH_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How many parsed log events may queue up before the parser starts dropping them. The watcher
+/// thread sends with `try_send` so it never blocks, and the receiving loop only does quick
+/// bookkeeping before spawning lookups, so the queue stays near empty. 256 covers a burst of chat
+/// (a whole game's kill feed) without letting a stalled receiver grow memory without bound.
+const LOG_EVENT_CAPACITY: usize = 256;
+
+/// State shared by every lobby lookup.
+struct LookupContext {
+    /// Shared with the health check, so both count against the same quota.
+    client: Arc<HypixelApiClient>,
+    friends_list: Option<Mutex<FriendsList>>,
+    boosters: Mutex<BoosterList>,
+    hypixel_up: Arc<AtomicBool>,
+    player_cache: std::sync::Mutex<PlayerCache>,
+    recent_games: std::sync::Mutex<RecentGamesCache>,
+    formatter: Box<dyn Formatter>,
+    telemetry_endpoint: Option<String>,
+    sink: Box<dyn OutputSink>,
+    thresholds: Thresholds,
+    /// Types `/who` when a game starts, if `command-fifo` is set.
+    commands: Option<CommandQueue>,
+    history: std::sync::Mutex<StatHistory>,
+    /// How many players are looked up at once.
+    parallelism: usize,
+    exporters: Vec<Arc<dyn StatsExporter>>,
+    /// Set by `redact-exports`.
+    redact_exports: bool,
+    /// Upper bound on a whole stats fetch, see `LOOKUP_TIMEOUT_MARGIN`.
+    lookup_timeout: Duration,
+}
+
+impl LookupContext {
+    async fn new(
+        config: &Config,
+        pool: Arc<ConnectionPool>,
+        hypixel_up: Arc<AtomicBool>,
+    ) -> Result<Self> {
+        let client = Arc::new(HypixelApiClient::new(config.api_key.clone(), pool));
+        let normalizer = load_normalizer(config, &client).await;
+        let mut boosters = BoosterList::default();
+        boosters.get(&client).await;
+        if let Err(e) = stat_history::compact_cache(Path::new(HISTORY_PATH), HISTORY_MAX_RECORDS) {
+            warn!("Failed to compact the stat history: {e:#}");
+        }
+        Ok(LookupContext {
+            client,
+            friends_list: config
+                .my_uuid
+                .clone()
+                .map(|uuid| Mutex::new(FriendsList::new(uuid))),
+            boosters: Mutex::new(boosters),
+            hypixel_up,
+            player_cache: std::sync::Mutex::new(PlayerCache::default()),
+            recent_games: std::sync::Mutex::new(RecentGamesCache::default()),
+            formatter: formatter::from_config(config.log_format, normalizer, config.display_arcade),
+            telemetry_endpoint: config.telemetry_endpoint.clone(),
+            sink: output::from_config(&config.output_sink).await?,
+            thresholds: config.thresholds.clone(),
+            commands: match &config.command_fifo {
+                Some(path) => {
+                    Some(CommandQueue::spawn(PathBuf::from(path), config.command_delay_ms).await?)
+                }
+                None => None,
+            },
+            history: std::sync::Mutex::new(StatHistory::load(PathBuf::from(HISTORY_PATH))?),
+            parallelism: config.parallelism.max(1),
+            exporters: export::from_config(&config.exporters),
+            redact_exports: config.redact_exports,
+            lookup_timeout: Duration::from_secs(config.request_timeout_secs)
+                + LOOKUP_TIMEOUT_MARGIN,
+        })
+    }
+}
+
+/// Fetches the reference player once at startup. The column is left out if that fails, rather
+/// than showing ratios against a missing player.
+async fn load_normalizer(config: &Config, client: &HypixelApiClient) -> Option<StatNormalizer> {
+    if !config
+        .display
+        .columns
+        .iter()
+        .any(|column| column == "norm_fkdr")
+    {
+        return None;
+    }
+    let uuid = config.normalize_by_uuid.clone()?;
+    if uuid_crate::Uuid::parse_str(&uuid).is_err() {
+        return None;
+    }
+
+    match get_hypixel_data(uuid, client).await {
+        Ok(reference) => {
+            info!("Normalizing stats by {}", reference.display_name());
+            Some(StatNormalizer::new(reference))
+        }
+        Err(e) => {
+            warn!("Could not fetch the normalize-by-uuid player, hiding norm_fkdr: {e}");
+            None
+        }
+    }
+}
+
+/// Looks up everyone from every `/who` in the log so far, prints them as one table and returns.
+/// The player cache only lives for the process, so every player is fetched.
+async fn run_once(config: Arc<Config>) -> Result<()> {
+    color::set_enabled(config.color_enabled());
+    display::set_rank_format(config.display.rank_format);
+    display::set_confidence_threshold(config.display.confidence_threshold);
+    let log = std::fs::read(&config.log_path)
+        .with_context(|| format!("Failed to read {}", config.log_path))?;
+    let log = String::from_utf8_lossy(&log);
+    let patterns = LogPatterns::new(
+        minecraft_version::detect_log_format(&log),
+        &config.lobby_countdown_pattern,
+    )?;
+
+    let mut online_events = 0;
+    let mut seen = HashSet::new();
+    let mut names = Vec::new();
+    for captures in log
+        .lines()
+        .filter_map(|line| patterns.online.captures(line))
+    {
+        online_events += 1;
+        for name in captures[1].split(", ") {
+            if seen.insert(name.to_lowercase()) {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    let table_config = Config {
+        log_format: LogFormat::Verbose,
+        ..(*config).clone()
+    };
+    let pool = Arc::new(ConnectionPool::new(&config)?);
+    let ctx =
+        Arc::new(LookupContext::new(&table_config, pool, Arc::new(AtomicBool::new(true))).await?);
+    info!(
+        "Found {online_events} ONLINE events with {} unique players",
+        names.len()
+    );
+
+    if !names.is_empty() {
+        lookup_lobby(
+            names,
+            Vec::new(),
+            config.game_mode,
+            LobbySource {
+                label: None,
+                instance: None,
+                alerts: AlertCooldown::default(),
+            },
+            HashMap::new(),
+            ctx,
+        )
+        .await;
+    }
+
+    Ok(())
 }
 
-impl std::default::Default for Config {
-    fn default() -> Self {
-        let mut log_path = dirs::home_dir().unwrap();
-        #[cfg(target_os = "windows")]
+async fn run(config: Arc<Config>) -> Result<()> {
+    color::set_enabled(config.color_enabled());
+    display::set_rank_format(config.display.rank_format);
+    display::set_confidence_threshold(config.display.confidence_threshold);
+    let hypixel_up = Arc::new(AtomicBool::new(true));
+    // Every API request of the session goes through these two clients
+    let pool = Arc::new(ConnectionPool::new(&config)?);
+    watchdog::spawn(Arc::clone(&hypixel_up), Arc::clone(&pool.hypixel));
+    let ctx = Arc::new(LookupContext::new(&config, pool, hypixel_up).await?);
+    HealthCheck::default().spawn(Arc::clone(&ctx.client));
+    let background_refresh = spawn_background_refresh(Arc::clone(&ctx));
+    let history_compaction = spawn_history_compaction(Arc::clone(&ctx));
+
+    // In multi-instance mode every event is tagged with the log it came from, so lobbies from
+    // different games are never mixed up
+    let multi_instance = !config.extra_log_paths.is_empty();
+    let log_paths: Vec<String> = std::iter::once(config.log_path.clone())
+        .chain(config.extra_log_paths.iter().cloned())
+        .collect();
+
+    // The parsers, driven by the watcher thread, send; the loop below receives and does all
+    // async work on this runtime.
+    let (event_tx, mut event_rx) = mpsc::channel(LOG_EVENT_CAPACITY);
+    let mut shutdown = ShutdownCoordinator::new(Duration::from_secs(config.shutdown_timeout_secs));
+    let mut hotwatch = Hotwatch::new().context("Failed to initialize the log watcher")?;
+    let mut pollers = Vec::new();
+    let health = Arc::new(std::sync::Mutex::new(WatcherHealth::new()));
+    for log_path in &log_paths {
+        let format = minecraft_version::detect_log_file_format(Path::new(log_path));
+        let parser = Arc::new(std::sync::Mutex::new(MinecraftLogParser::new(
+            PathBuf::from(log_path),
+            LogPatterns::new(format, &config.lobby_countdown_pattern)?,
+            event_tx.clone(),
+            multi_instance,
+        )));
+        let process_log: LogHandler = Arc::new(move || parser.lock().unwrap().process_event());
+
+        if !watcher::soft_reset_log_watcher(
+            &mut hotwatch,
+            log_path,
+            Arc::clone(&process_log),
+            &health,
+        )
+        .await
         {
-            log_path.push("AppData");
-            log_path.push("Roaming");
+            warn!("Falling back to polling mode — consider increasing fs.inotify.max_user_watches");
+            pollers.push(watcher::spawn_polling(
+                log_path.clone(),
+                Duration::from_secs(config.poll_interval_secs),
+                process_log,
+            ));
+        }
+    }
+    drop(event_tx);
+    shutdown.register("log watcher").on_shutdown(async move {
+        drop(hotwatch);
+        for poller in pollers {
+            poller.abort();
         }
-        log_path.push(".minecraft");
-        log_path.push("logs");
-        log_path.push("latest.log");
-
-        Config {
-            log_path: log_path.display().to_string(),
-            api_key: "INSERT_API_KEY_HERE".to_string(),
-            quit_level: 130,
+    });
+    shutdown
+        .register("background tasks")
+        .on_shutdown(async move {
+            background_refresh.abort();
+            history_compaction.abort();
+        });
+    let lookups_shutdown = shutdown.register("player lookups");
+    // Records are appended as they come in; once the last lookup is done the file is compacted
+    let history_ctx = Arc::clone(&ctx);
+    shutdown.register("stat history").on_shutdown(async move {
+        if let Err(e) = history_ctx
+            .history
+            .lock()
+            .unwrap()
+            .compact(HISTORY_MAX_RECORDS)
+        {
+            warn!("Failed to compact the stat history: {e:#}");
         }
+    });
+    let mut lookups = JoinSet::new();
+
+    let mut instances: HashMap<Option<PathBuf>, InstanceState> = HashMap::new();
+
+    let who_timeout = Duration::from_secs(config.who_timeout_secs);
+    loop {
+        let who_deadline = instances
+            .values()
+            .filter_map(|instance| instance.who_deadline)
+            .min();
+        let event = tokio::select! {
+            event = event_rx.recv() => event,
+            Some(_) = lookups.join_next() => continue,
+            // Fires even while the log is silent, which is when a missing /who matters
+            () = tokio::time::sleep_until(who_deadline.unwrap_or_else(Instant::now).into()),
+                if who_deadline.is_some() =>
+            {
+                let now = Instant::now();
+                for instance in instances.values_mut() {
+                    if instance.take_overdue_who(now) {
+                        warn!(
+                            "No ONLINE response received within {}s — either /who was not typed or the server is lagging",
+                            who_timeout.as_secs()
+                        );
+                    }
+                }
+                continue;
+            }
+            signal = tokio::signal::ctrl_c() => {
+                signal.context("Failed to listen for CTRL+C")?;
+                info!("{}", metrics().summary());
+                let session: GlobalSessionStats =
+                    instances.values().map(|instance| instance.session).collect();
+                info!("{}", session.summary());
+                warn!("Received CTRL+C. Closing");
+                break;
+            }
+        };
+        let Some(event) = event else {
+            break;
+        };
+
+        let label = event.source_instance.as_ref().and_then(|source| {
+            let index = log_paths
+                .iter()
+                .position(|path| Path::new(path) == source)?;
+            Some(format!("[I{}]", index + 1))
+        });
+        let instance = instances
+            .entry(event.source_instance.clone())
+            .or_insert_with(|| InstanceState::new(config.game_mode));
+
+        let (names, cached, lobby_id) = match event.kind {
+            LogEventKind::ModeDetected(mode) => {
+                info!("Detected Bedwars mode: {mode}");
+                instance.mode = Some(mode);
+                instance.who_deadline = Some(Instant::now() + who_timeout);
+                instance.game_state = GameState::default();
+                instance.session.games += 1;
+                instance.alert_cooldown.reset();
+                if let Some(commands) = ctx.commands.as_ref().filter(|_| instance.on_hypixel) {
+                    commands.push("/who");
+                }
+                continue;
+            }
+            LogEventKind::BedDestroyed { team, destroyer } => {
+                instance.game_state.bed_destroyed(&team, &destroyer);
+                instance.session.beds_destroyed += 1;
+                continue;
+            }
+            LogEventKind::FinalKill(victim) => {
+                instance.game_state.final_kill(&victim);
+                instance.session.final_kills += 1;
+                continue;
+            }
+            LogEventKind::TeamMember { team, player } => {
+                instance.game_state.team_member(team, &player);
+                continue;
+            }
+            LogEventKind::ServerConnected(Server::Hypixel(region)) => {
+                info!("Connected to Hypixel, {region} region");
+                metrics().set_region(region);
+                instance.on_hypixel = true;
+                continue;
+            }
+            LogEventKind::ServerConnected(Server::Other(host)) => {
+                info!("Connected to {host}");
+                info!("Not connected to Hypixel — overlay inactive");
+                instance.on_hypixel = false;
+                continue;
+            }
+            _ if !instance.on_hypixel => continue,
+            LogEventKind::Who(names) => {
+                instance.who_deadline = None;
+                info!("/who has been executed");
+                metrics().who_event();
+                instance.session.lobbies += 1;
+                info!("Names: {:?}", names);
+                let lobby_id = logging::new_lobby_id();
+                instance.lobby_snapshot = Some(LobbySnapshot {
+                    names: names.clone(),
+                    source_instance: event.source_instance.clone(),
+                    players: Vec::new(),
+                    lobby_id: lobby_id.clone(),
+                    mode: instance.mode,
+                    teams: instance.game_state.teams.clone(),
+                });
+                (names, Vec::new(), lobby_id)
+            }
+            LogEventKind::LobbyCountdown => {
+                // Every tick of the countdown matches, only the first reprints the lobby
+                let Some(snapshot) = instance.lobby_snapshot.take() else {
+                    continue;
+                };
+                let (cached, missing) = ctx.player_cache.lock().unwrap().partition(&snapshot.names);
+                info!(
+                    "Game starting — showing cached stats for {} of {} players",
+                    cached.len(),
+                    snapshot.names.len()
+                );
+                // The countdown reprints the lobby of the last /who, so it keeps that lobby's id
+                (missing, cached, snapshot.lobby_id)
+            }
+        };
+
+        metrics().cache_lookup(cached.len(), names.len());
+        let player_count = names.len() + cached.len();
+        let mode = instance.mode.filter(|&mode| {
+            let fits = player_count <= mode.max_players();
+            match validate_lobby_size(mode, player_count) {
+                Some(warning) if !fits => warn!("{warning}, showing lifetime stats"),
+                Some(warning) => warn!("{warning}"),
+                None => {}
+            }
+            fits
+        });
+        lookups.spawn(logging::in_lobby(
+            lobby_id,
+            lookup_lobby(
+                names,
+                cached,
+                mode,
+                LobbySource {
+                    label,
+                    instance: event.source_instance,
+                    alerts: instance.alert_cooldown.clone(),
+                },
+                instance.game_state.teams.clone(),
+                Arc::clone(&ctx),
+            ),
+        ));
     }
+
+    lookups_shutdown.on_shutdown(async move { while lookups.join_next().await.is_some() {} });
+    shutdown.shutdown().await;
+
+    Ok(())
 }
 
-#[derive(Deserialize)]
-struct Player {
-    name: String,
-    id: String,
+/// What the overlay knows about the game running in one Minecraft instance.
+struct InstanceState {
+    mode: Option<BedwarsMode>,
+    game_state: GameState,
+    /// The lobby of the last `/who`, until the pre-game countdown reprints it.
+    lobby_snapshot: Option<LobbySnapshot>,
+    session: SessionStats,
+    /// Cleared while the client is on another server, where lobbies aren't looked up. Starts
+    /// set, since the overlay may start after the client already joined.
+    on_hypixel: bool,
+    /// When the `/who` expected since the last game was joined is overdue, see
+    /// `who-timeout-secs`.
+    who_deadline: Option<Instant>,
+    /// Who was alerted about in this instance's lobby, reset when it joins a game.
+    alert_cooldown: AlertCooldown,
 }
 
-const CONFIG_PATH: &str = "config.toml";
+impl InstanceState {
+    fn new(mode: Option<BedwarsMode>) -> Self {
+        InstanceState {
+            mode,
+            game_state: GameState::default(),
+            lobby_snapshot: None,
+            session: SessionStats::default(),
+            on_hypixel: true,
+            who_deadline: None,
+            alert_cooldown: AlertCooldown::default(),
+        }
+    }
 
-async fn read_config() -> Result<Config> {
-    let exists = matches!(fs::try_exists("config.toml").await, Ok(true));
+    /// Whether the expected `/who` is overdue at `now`. Each one is only reported once.
+    fn take_overdue_who(&mut self, now: Instant) -> bool {
+        let overdue = self.who_deadline.is_some_and(|deadline| deadline <= now);
+        if overdue {
+            self.who_deadline = None;
+        }
+        overdue
+    }
+}
 
-    if !exists {
-        info!("Creating config file at {CONFIG_PATH}");
-        let mut f = File::create(CONFIG_PATH).await?;
+/// How many past lookups the FKDR trend is fitted over.
+const FKDR_TREND_POINTS: usize = 10;
 
-        info!("Generating default config");
-        let config = Config::default();
-        let config_str = toml::to_string(&config)?;
-        let _ = f.write_all(config_str.as_bytes()).await;
+/// Writes `player` in the configured format, raises any alert and adds them to `lobby`.
+async fn show(
+    ctx: &LookupContext,
+    alerts: &AlertCooldown,
+    lobby: &mut Vec<HypixelPlayer>,
+    mode: Option<BedwarsMode>,
+    label: Option<&str>,
+    player: HypixelPlayer,
+) {
+    let line = ctx.formatter.format_player(&player, mode);
+    if !line.is_empty() {
+        write_output(ctx, label, &line).await;
+    }
+    if let Some(alert) = PlayerAlert::check(player.clone(), &ctx.thresholds) {
+        if alerts.first_in_lobby(&alert) {
+            alert.dispatch();
+        }
     }
+    lobby.push(player);
+}
 
-    let config_str = std::fs::read_to_string(CONFIG_PATH)?;
-    let mut config: Config = toml::from_str(&config_str)?;
-    let mut log_path = PathBuf::from(&config.log_path);
-    if !log_path.ends_with("latest.log") {
-        warn!("Log path is not pointing to latest.log, pushing it to path");
-        log_path.push("latest.log");
+/// Writes `data`, prefixing every line with the instance `label` in multi-instance mode.
+async fn write_output(ctx: &LookupContext, label: Option<&str>, data: &str) {
+    let data = match label {
+        Some(label) => data
+            .lines()
+            .map(|line| format!("{label} {line}"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        None => data.to_string(),
+    };
+    if let Err(e) = ctx.sink.write(&data).await {
+        error!("Error while writing output: {e:#}");
     }
-    config.log_path = log_path.to_string_lossy().to_string();
+}
 
-    Ok(config)
+/// The Minecraft instance a lobby was seen in.
+struct LobbySource {
+    /// Put in front of every line in multi-instance mode, e.g. `[I1]`.
+    label: Option<String>,
+    /// The instance's log, `None` with a single instance.
+    instance: Option<PathBuf>,
+    /// The instance's own cooldown, so a game joined in one doesn't re-arm another's alerts.
+    alerts: AlertCooldown,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    simple_logger::SimpleLogger::new()
-        .env()
-        .with_level(LevelFilter::Info)
-        .init()
-        .unwrap();
-
-    let config = Arc::new(read_config().await?);
-    let rt = Arc::new(Runtime::new()?);
-    let last_processed_line = Arc::new(std::sync::Mutex::new(String::new()));
-
-    let mut hotwatch = Hotwatch::new()?;
-    info!("Watching log path: {}", config.log_path);
-    hotwatch.watch(config.log_path.clone(), {
-        let config = Arc::clone(&config);
-        let rt = Arc::clone(&rt);
-        let last_processed_line = Arc::clone(&last_processed_line);
-
-        move |event| {
-            if let EventKind::Modify(_) = event.kind {
-                let log = match std::fs::read_to_string(&config.log_path) {
-                    Ok(l) => l,
-                    Err(e) => {
-                        eprintln!("Error reading log: {e}");
-                        return;
+/// Prints `cached` and then looks up and prints everyone in `names`.
+async fn lookup_lobby(
+    names: Vec<String>,
+    cached: Vec<HypixelPlayer>,
+    mode: Option<BedwarsMode>,
+    source: LobbySource,
+    teams: HashMap<String, TeamColor>,
+    ctx: Arc<LookupContext>,
+) {
+    let LobbySource {
+        label,
+        instance: source_instance,
+        alerts,
+    } = source;
+    let label = label.as_deref();
+    info!("Getting player uuids");
+    let (mut resolved, resolver) = stream_player_uuids(ctx.client.mojang().clone(), names);
+    let mut resolver = Some(resolver);
+
+    let boosters = Arc::new(ctx.boosters.lock().await.get(&ctx.client).await.clone());
+    let mut lobby: Vec<HypixelPlayer> = Vec::new();
+    for mut player in cached {
+        // Boosters run out or start while a player sits in the cache
+        player.has_active_booster = boosters::has_active_booster(&boosters, &player.uuid);
+        show(&ctx, &alerts, &mut lobby, mode, label, player).await;
+    }
+
+    let friends = Arc::new(match &ctx.friends_list {
+        Some(list) => list.lock().await.get(&ctx.client).await.clone(),
+        None => HashSet::new(),
+    });
+    let mut friends_in_lobby = Vec::new();
+
+    // Lookups start as soon as the first Mojang chunk resolves, the heap only orders the players
+    // that are already waiting for a free slot.
+    let mut queue: BinaryHeap<PrioritizedLookup> = BinaryHeap::new();
+    let mut lookups = JoinSet::new();
+
+    loop {
+        while lookups.len() < ctx.parallelism {
+            let Some(next) = queue.pop() else {
+                break;
+            };
+            debug!("Queueing {} with {:?} priority", next.name, next.priority);
+            lookups.spawn(logging::in_current_lobby(lookup_player(
+                next.uuid,
+                next.name,
+                Arc::clone(&friends),
+                Arc::clone(&boosters),
+                Arc::clone(&ctx),
+            )));
+        }
+
+        if resolver.is_none() && lookups.is_empty() {
+            break;
+        }
+
+        let result = tokio::select! {
+            pair = resolved.recv(), if resolver.is_some() => {
+                match pair {
+                    Some((uuid, name)) => {
+                        let priority = ctx.player_cache.lock().unwrap().lookup_priority(&name);
+                        queue.push(PrioritizedLookup { priority, name, uuid });
                     }
-                };
+                    None => {
+                        let unresolved = match resolver.take().unwrap().await {
+                            Ok(unresolved) => unresolved,
+                            Err(e) => {
+                                error!("Error while getting player uuids: {e}");
+                                Vec::new()
+                            }
+                        };
+                        if !unresolved.is_empty() {
+                            warn!("Could not resolve UUIDs for: {}", unresolved.join(", "));
+                        }
+                        for name in &unresolved {
+                            show(&ctx, &alerts, &mut lobby, mode, label, HypixelPlayer::unknown(name)).await;
+                        }
+                    }
+                }
+                continue;
+            }
+            Some(result) = lookups.join_next() => result,
+        };
+        let player = match result {
+            Ok(Some(player)) => player,
+            Ok(None) => continue,
+            Err(e) => {
+                error!("Player lookup task failed: {e}");
+                continue;
+            }
+        };
+        if player.is_friend {
+            friends_in_lobby.push(player.display_name());
+        }
+        show(&ctx, &alerts, &mut lobby, mode, label, player).await;
+    }
 
-                let last_line = log
-                    .lines()
-                    .rev()
-                    .find(|line| !line.trim().is_empty())
-                    .unwrap_or("");
-                info!("Last line: {}", last_line);
-
-                // Check for duplicates
-                {
-                    let mut stored_line = last_processed_line.lock().unwrap();
-                    if last_line == *stored_line {
-                        return;
+    let snapshot = LobbySnapshot {
+        names: lobby.iter().map(|player| player.name.clone()).collect(),
+        source_instance,
+        players: lobby,
+        lobby_id: logging::current_lobby_id().unwrap_or_default(),
+        mode,
+        teams,
+    };
+    let table = ctx.formatter.format_lobby(&snapshot);
+    if !table.is_empty() {
+        write_output(&ctx, label, &table).await;
+    }
+
+    if friends_in_lobby.len() > 1 {
+        info!(
+            "Friends sharing this lobby: {}",
+            friends_in_lobby.join(", ")
+        );
+    }
+
+    if !ctx.exporters.is_empty() {
+        let snapshot = if ctx.redact_exports {
+            snapshot.redacted()
+        } else {
+            snapshot
+        };
+        export::export_all(&ctx.exporters, snapshot).await;
+    }
+}
+
+/// Fetches, caches and records one player's stats. Failed lookups come back as an unknown player
+/// so they still get a row, `None` means the lookup was skipped while Hypixel is down.
+async fn lookup_player(
+    uuid: Uuid,
+    player: String,
+    friends: Arc<HashSet<Uuid>>,
+    boosters: Arc<HashSet<Uuid>>,
+    ctx: Arc<LookupContext>,
+) -> Option<HypixelPlayer> {
+    info!(uuid = uuid.as_str(), player_name = player.as_str(); "Getting hypixel data for {}", uuid);
+    info!(uuid = uuid.as_str(), player_name = player.as_str(); "UUID for {}: {}", player, uuid);
+    if !ctx.hypixel_up.load(Ordering::Relaxed) {
+        warn!("Hypixel API appears down, skipping lookup");
+        return None;
+    }
+    let is_friend = friends::is_friend(&friends, &uuid);
+    let started = Instant::now();
+    let Some(fetched) =
+        get_hypixel_data_within(uuid.clone(), &ctx.client, ctx.lookup_timeout).await
+    else {
+        error!(uuid = uuid.as_str(), player_name = player.as_str(); "Timeout fetching data for {player} after {}s", ctx.lookup_timeout.as_secs());
+        metrics().api_error();
+        return Some(HypixelPlayer {
+            uuid,
+            ..HypixelPlayer::unknown(&player)
+        });
+    };
+    let mut hypixel_data = match fetched {
+        Ok(hypixel_data) => {
+            metrics().player_looked_up(&uuid, started.elapsed());
+            hypixel_data
+        }
+        Err(e) => {
+            match e.downcast_ref::<PlayerLookupError>() {
+                Some(not_found @ PlayerLookupError::PlayerNotFound { .. }) => {
+                    info!(uuid = uuid.as_str(), player_name = player.as_str(); "{not_found}")
+                }
+                _ => {
+                    error!(uuid = uuid.as_str(), player_name = player.as_str(); "Error while getting data from hypixel: {e}");
+                    if let Some(endpoint) = &ctx.telemetry_endpoint {
+                        telemetry::report(endpoint, &e);
                     }
-                    *stored_line = last_line.to_string();
                 }
+            }
+            return Some(HypixelPlayer {
+                uuid,
+                ..HypixelPlayer::unknown(&player)
+            });
+        }
+    };
 
-                let player_regex = Regex::new(r"\[CHAT\] ONLINE: (.*)").unwrap();
-
-                if player_regex.is_match(last_line) {
-                    info!("/who has been executed");
-                    let captures = player_regex.captures(last_line).unwrap();
-                    let cleaned_line = captures.get(1).unwrap().as_str();
-                    info!("Cleaned line: {}", cleaned_line);
-
-                    let names: Vec<String> =
-                        cleaned_line.split(", ").map(|x| x.to_string()).collect();
-                    info!("Names: {:?}", names);
-                    // Only god knows why this works.
-                    let value = config.clone();
-                    rt.spawn(async move {
-                        info!("Getting player uuids");
-                        let players = get_player_uuids(names)
-                            .await
-                            .map_err(|e| {
-                                error!("Error while getting player uuids: {e}");
-                            })
-                            .unwrap();
-
-                        for (uuid, player) in players {
-                            info!("Getting hypixel data for {}", uuid);
-                            let config = value.clone();
-                            info!("UUID for {}: {}", player, uuid);
-                            let hypixel_data = get_hypixel_data(uuid, config)
-                                .await
-                                .map_err(|e| {
-                                    error!("Error while getting data from hypixel: {e}");
-                                })
-                                .unwrap();
-
-                            eprintln!("{:#?}", hypixel_data);
+    hypixel_data.is_friend = is_friend;
+    hypixel_data.has_active_booster = boosters::has_active_booster(&boosters, &uuid);
+    // Not held across the request, so lookups of other players don't queue behind it
+    let cached_games = ctx.recent_games.lock().unwrap().cached(&uuid);
+    hypixel_data.games_last_24h = match cached_games {
+        Some(count) => count,
+        None => {
+            let count = recent_games::fetch_games_last_24h(&uuid, &ctx.client).await;
+            ctx.recent_games.lock().unwrap().insert(uuid.clone(), count);
+            count
+        }
+    };
+
+    {
+        let mut cache = ctx.player_cache.lock().unwrap();
+        let refetch = if cache.take_evicted(&uuid) {
+            " (re-fetch after eviction)"
+        } else {
+            ""
+        };
+        info!(uuid = uuid.as_str(), player_name = player.as_str(); "Fetched stats for {}{refetch}", hypixel_data.display_name());
+        cache.insert(&player, hypixel_data.clone());
+    }
+    {
+        let mut history = ctx.history.lock().unwrap();
+        if let Err(e) = history.record(&hypixel_data) {
+            error!("Error while saving stat history: {e:#}");
+        }
+        if let Some(trend) = history.fkdr_trend(&uuid, FKDR_TREND_POINTS) {
+            debug!(
+                "FKDR trend for {}: {trend:+.3}",
+                hypixel_data.display_name()
+            );
+        }
+    }
+
+    Some(hypixel_data)
+}
+
+const MOJANG_ATTEMPTS: u32 = 2;
+const MOJANG_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Resolves `names` to UUIDs, returning the resolved players keyed by UUID alongside the names
+/// that could not be resolved by either Mojang or the fallback API.
+async fn get_player_uuids(
+    client: &Client,
+    names: Vec<String>,
+) -> Result<(HashMap<Uuid, String>, Vec<String>)> {
+    let (mut resolved, resolver) = stream_player_uuids(client.clone(), names);
+
+    let mut mojang_players: HashMap<Uuid, String> = HashMap::new();
+    while let Some((uuid, name)) = resolved.recv().await {
+        mojang_players.insert(uuid, name);
+    }
+    let unresolved = resolver.await.context("UUID resolver task failed")?;
+
+    Ok((mojang_players, unresolved))
+}
+
+/// Resolves `names` to UUIDs in the background, sending each chunk's players as soon as Mojang
+/// answers for it. The handle yields the names nobody could resolve once the channel closes.
+fn stream_player_uuids(
+    client: Client,
+    names: Vec<String>,
+) -> (mpsc::Receiver<(Uuid, String)>, JoinHandle<Vec<String>>) {
+    let (tx, rx) = mpsc::channel(names.len().max(1));
+
+    let resolver = tokio::spawn(logging::in_current_lobby(async move {
+        let mut unresolved: Vec<String> = Vec::new();
+
+        for chunk in names.chunks(10) {
+            let players = match lookup_mojang_chunk(&client, chunk).await {
+                Some(players) => {
+                    // Names missing from the bulk response are unknown to Mojang, usually nicks.
+                    for name in chunk {
+                        if !players.iter().any(|p| p.name.eq_ignore_ascii_case(name)) {
+                            unresolved.push(name.clone());
                         }
-                    });
+                    }
+                    players
+                }
+                None => handle_mojang_failure(&client, chunk, &mut unresolved).await,
+            };
+
+            for player in players {
+                if tx.send((player.id, player.name)).await.is_err() {
+                    debug!("UUID receiver dropped, stopping resolution");
+                    return unresolved;
                 }
             }
         }
-    })?;
 
-    // Keep the program running indefinitely
-    tokio::signal::ctrl_c().await?;
-    warn!("Received CTRL+C. Closing");
+        unresolved
+    }));
 
-    Ok(())
+    (rx, resolver)
 }
 
-async fn get_player_uuids(names: Vec<String>) -> Result<HashMap<String, Uuid>> {
-    let client = Client::new();
-    let chunks: Vec<&[String]> = names.chunks(10).collect();
-
-    let mut mojang_players: HashMap<String, Uuid> = HashMap::new();
+/// Runs the bulk lookup for one chunk, retrying once before giving up on it.
+async fn lookup_mojang_chunk(client: &Client, chunk: &[String]) -> Option<Vec<Player>> {
+    let body = json!(chunk);
 
-    for chunk in chunks {
-        let body = json!(chunk);
+    for attempt in 1..=MOJANG_ATTEMPTS {
+        metrics().mojang_call();
+        let started = Instant::now();
         let response_res = client
             .post("https://api.minecraftservices.com/minecraft/profile/lookup/bulk/byname")
             .header("content-type", "application/json")
             .json(&body)
             .send()
             .await;
+        if let Ok(resp) = &response_res {
+            metrics().request_completed(Api::Mojang, resp.remote_addr(), started.elapsed());
+        }
 
-        if let Ok(resp) = response_res {
-            if !resp.status().is_success() {
-                handle_mojang_failure(&client, chunk, &mut mojang_players).await?;
-                continue;
+        match response_res {
+            Ok(resp) => {
+                let status = resp.status();
+                match resp.text().await {
+                    Ok(text) if status.is_success() => match serde_json::from_str(&text) {
+                        Ok(players) => return Some(players),
+                        Err(source) => {
+                            let e = PlayerLookupError::ParseError {
+                                source,
+                                context: format!("Mojang response for {chunk:?}"),
+                            };
+                            warn!("{e}");
+                        }
+                    },
+                    Ok(text) => {
+                        let e = PlayerLookupError::MojangApiError {
+                            status: status.as_u16(),
+                            body: body_snippet(&text),
+                        };
+                        warn!("{e} for {chunk:?}");
+                    }
+                    Err(e) => warn!("Failed to read Mojang response for {chunk:?}: {e}"),
+                }
             }
+            Err(e) => warn!("Failed to reach Mojang API for {:?}: {e}", chunk),
+        }
+        metrics().api_error();
 
-            let players: Vec<Player> = resp.json().await?;
-
-            for player in players {
-                mojang_players.insert(player.id, player.name);
-            }
+        if attempt < MOJANG_ATTEMPTS {
+            tokio::time::sleep(MOJANG_RETRY_DELAY).await;
         }
     }
 
-    Ok(mojang_players)
+    None
 }
 
 async fn handle_mojang_failure(
     client: &Client,
     chunk: &[String],
-    mojang_players: &mut HashMap<String, Uuid>,
-) -> Result<()> {
+    unresolved: &mut Vec<String>,
+) -> Vec<Player> {
     warn!("There was an error returned from Mojang API.");
     warn!("Retrying using fallback api (api.minetools.eu)...");
 
+    let mut players = Vec::new();
     for player in chunk {
+        metrics().mojang_call();
         let response_res = client
             .get(format!("https://api.minetools.eu/uuid/{}", player))
             .send()
             .await;
 
-        if let Ok(resp) = response_res {
-            let api_player: Player = resp.json().await?;
-            mojang_players.insert(api_player.id, api_player.name);
+        let api_player = match response_res {
+            Ok(resp) if resp.status().is_success() => resp.json::<Player>().await.ok(),
+            _ => None,
+        };
+
+        match api_player {
+            Some(api_player) => players.push(api_player),
+            None => unresolved.push(player.clone()),
         }
     }
 
-    Ok(())
+    players
 }
 
-async fn get_hypixel_data(uuid: Uuid, config: Arc<Config>) -> Result<HypixelPlayer> {
-    info!("UUID being passed: {uuid}");
-    let hypixel_uuid = uuid_crate::Uuid::parse_str(&uuid)
-        .map_err(|e| {
-            error!("Invalid UUID format: {e}");
-        })
-        .unwrap();
+/// The undashed, lowercase form UUIDs are compared in. Hypixel and Mojang don't agree on
+/// whether to dash them, so lists from either can be matched against lobby players.
+fn comparable_uuid(uuid: &str) -> Uuid {
+    uuid.replace('-', "").to_lowercase()
+}
 
-    let url = format!(
-        "https://api.hypixel.net/player?key={}&uuid={}",
-        config.api_key, hypixel_uuid
-    );
+/// Mojang hands out UUIDs without dashes while Hypixel expects them dashed. Accepts either form
+/// and returns the dashed one.
+fn normalize_uuid(raw: &str) -> Result<String, PlayerLookupError> {
+    let uuid = uuid_crate::Uuid::parse_str(raw.trim()).map_err(|source| {
+        PlayerLookupError::UuidParseError {
+            source,
+            raw: raw.to_string(),
+        }
+    })?;
+    Ok(uuid.hyphenated().to_string())
+}
 
-    let client = Client::new();
-    let response = client.get(&url).send().await?;
+async fn get_hypixel_data(uuid: Uuid, client: &HypixelApiClient) -> Result<HypixelPlayer> {
+    info!("UUID being passed: {uuid}");
+    let hypixel_uuid = normalize_uuid(&uuid)?;
+
+    let body = client.get("player", &hypixel_uuid).await?;
 
-    let status = response.status();
-    let body = response.text().await?;
+    let value: serde_json::Value =
+        serde_json::from_str(&body).map_err(|source| PlayerLookupError::ParseError {
+            source,
+            context: format!("Hypixel player response (body: {})", body_snippet(&body)),
+        })?;
 
-    if !status.is_success() {
-        error!("Hypixel API returned an error: {}", body);
-        return Err(anyhow::anyhow!("Hypixel API error: {}", status));
+    if value["player"].is_null() {
+        return Err(PlayerLookupError::PlayerNotFound { uuid_or_name: uuid }.into());
     }
 
-    let parsed: Result<HypixelPlayer, _> = serde_json::from_str(&body);
+    Ok(HypixelPlayer::try_from(value)?)
+}
 
-    if let Err(e) = &parsed {
-        error!(
-            "Failed to parse Hypixel API response: {}\nBody: {}",
-            e, body
-        );
-    }
+/// How often the player cache is searched for entries about to expire.
+const BACKGROUND_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Fetches players shown in a recent lobby again shortly before their cache entries expire, so a
+/// player met again later in the session is shown from the cache instead of waiting on the API.
+/// Goes through the same `HypixelApiClient`, and with it the same rate limit, as lobby lookups.
+/// Aborting the task cancels the refreshes still running.
+fn spawn_background_refresh(ctx: Arc<LookupContext>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(BACKGROUND_REFRESH_INTERVAL);
+        let mut refreshes = JoinSet::new();
+
+        loop {
+            interval.tick().await;
+            while refreshes.try_join_next().is_some() {}
+            if !ctx.hypixel_up.load(Ordering::Relaxed) {
+                continue;
+            }
 
-    parsed.map_err(|e| anyhow::anyhow!("Failed to parse Hypixel API response: {}", e))
+            let due = ctx.player_cache.lock().unwrap().due_for_refresh();
+            for (name, uuid) in due {
+                let ctx = Arc::clone(&ctx);
+                refreshes.spawn(async move {
+                    debug!("Background refresh for {name}");
+                    match get_hypixel_data_within(uuid, &ctx.client, ctx.lookup_timeout).await {
+                        Some(Ok(player)) => ctx.player_cache.lock().unwrap().refresh(&name, player),
+                        Some(Err(e)) => debug!("Background refresh for {name} failed: {e}"),
+                        None => debug!("Background refresh for {name} timed out"),
+                    }
+                });
+            }
+        }
+    })
+}
+
+/// How often `history.json` is compacted while the overlay runs.
+const HISTORY_COMPACTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Compacts the stat history every `HISTORY_COMPACTION_INTERVAL`, so long sessions don't grow it
+/// without bound. It is also compacted once at startup.
+fn spawn_history_compaction(ctx: Arc<LookupContext>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(HISTORY_COMPACTION_INTERVAL);
+        // The first tick fires right away, and startup has just compacted
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = ctx.history.lock().unwrap().compact(HISTORY_MAX_RECORDS) {
+                warn!("Failed to compact the stat history: {e:#}");
+            }
+        }
+    })
+}
+
+/// Added to `request-timeout-secs` for the bound on a whole stats fetch. reqwest's own timeout
+/// fires first for slow requests, so this one only catches what comes after, e.g. parsing a huge
+/// body, and the two are told apart in the log.
+const LOOKUP_TIMEOUT_MARGIN: Duration = Duration::from_secs(5);
+
+/// `get_hypixel_data` given at most `timeout`, `None` once that has passed.
+async fn get_hypixel_data_within(
+    uuid: Uuid,
+    client: &HypixelApiClient,
+    timeout: Duration,
+) -> Option<Result<HypixelPlayer>> {
+    tokio::time::timeout(timeout, get_hypixel_data(uuid, client))
+        .await
+        .ok()
 }
 
 // TODO: uncomment this later and replace the get_hypixel_data function with this one
@@ -287,8 +1138,8 @@ async fn get_hypixel_data(uuid: Uuid, config: Arc<Config>) -> Result<HypixelPlay
 //                 anyhow::bail!("response is not ok: {}", resp.status());
 //             }
 //             let hypixel_data: ApiHypixelData = resp.json().await?;
-//             if hypixel_data.player.is_some() {
-//                 return Ok(HypixelPlayer::from_api(hypixel_data.player.unwrap(), uuid));
+//             if let Some(player) = hypixel_data.player {
+//                 return Ok((player, uuid).into());
 //             }
 //         }
 //         Err(e) => {
@@ -298,3 +1149,89 @@ async fn get_hypixel_data(uuid: Uuid, config: Arc<Config>) -> Result<HypixelPlay
 //
 //     anyhow::bail!("response is not ok");
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_helpers::MockHypixelServer;
+
+    #[tokio::test]
+    async fn hypixel_data_from_the_player_endpoint() {
+        let server = MockHypixelServer::start().await;
+        server.register_player(
+            "069a79f4-44e9-4726-a5be-fca90e38aaf5",
+            "player_social_media",
+        );
+        let client =
+            HypixelApiClient::new("key".to_string(), ConnectionPool::shared(Client::new()))
+                .with_base_url(server.base_url());
+
+        let notch = get_hypixel_data("069a79f4-44e9-4726-a5be-fca90e38aaf5".to_string(), &client)
+            .await
+            .unwrap();
+        assert_eq!(notch.name, "Notch");
+        assert_eq!(notch.level, 42);
+
+        let unknown = "00000000-0000-0000-0000-000000000000".to_string();
+        let err = get_hypixel_data(unknown, &client).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PlayerLookupError>(),
+            Some(PlayerLookupError::PlayerNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn slow_lookups_time_out() {
+        let server = MockHypixelServer::start().await;
+        server.register_player(
+            "069a79f4-44e9-4726-a5be-fca90e38aaf5",
+            "player_social_media",
+        );
+        let client =
+            HypixelApiClient::new("key".to_string(), ConnectionPool::shared(Client::new()))
+                .with_base_url(server.base_url());
+        let notch = || "069a79f4-44e9-4726-a5be-fca90e38aaf5".to_string();
+
+        let fetched = get_hypixel_data_within(notch(), &client, Duration::from_secs(5)).await;
+        assert_eq!(fetched.unwrap().unwrap().name, "Notch");
+
+        server.set_delay(Duration::from_secs(5));
+        let fetched = get_hypixel_data_within(notch(), &client, Duration::from_millis(100)).await;
+        assert!(fetched.is_none());
+    }
+
+    #[test]
+    fn unanswered_who_is_reported_once() {
+        let mut instance = InstanceState::new(None);
+        let joined = Instant::now();
+        instance.who_deadline = Some(joined + Duration::from_secs(10));
+
+        assert!(!instance.take_overdue_who(joined + Duration::from_secs(5)));
+        assert!(instance.take_overdue_who(joined + Duration::from_secs(10)));
+        assert!(!instance.take_overdue_who(joined + Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn instances_keep_their_own_alert_cooldown() {
+        let (first, second) = (InstanceState::new(None), InstanceState::new(None));
+        let alert =
+            PlayerAlert::check(HypixelPlayer::unknown("Nick"), &Thresholds::default()).unwrap();
+
+        assert!(first.alert_cooldown.first_in_lobby(&alert));
+        assert!(second.alert_cooldown.first_in_lobby(&alert));
+        // Joining a game in the second instance leaves the first one's lobby alone
+        second.alert_cooldown.reset();
+        assert!(!first.alert_cooldown.first_in_lobby(&alert));
+    }
+
+    #[test]
+    fn uuids_are_normalized_to_dashed_form() {
+        let dashed = "069a79f4-44e9-4726-a5be-fca90e38aaf5";
+        assert_eq!(normalize_uuid(dashed).unwrap(), dashed);
+        assert_eq!(
+            normalize_uuid("069a79f444e94726a5befca90e38aaf5").unwrap(),
+            dashed
+        );
+        assert!(normalize_uuid("069a79f444e94726a5be").is_err());
+    }
+}
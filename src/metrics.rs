@@ -0,0 +1,227 @@
+use log::info;
+use std::{
+    collections::HashSet,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        LazyLock, Mutex,
+    },
+    time::Duration,
+};
+
+use crate::{lobby::EvictionReason, server::HypixelRegion, Uuid};
+
+static METRICS: LazyLock<SessionMetrics> = LazyLock::new(SessionMetrics::default);
+
+/// The counters for this session, shared by every part of the pipeline.
+pub fn metrics() -> &'static SessionMetrics {
+    &METRICS
+}
+
+/// Requests faster than this count as fast, slower than `SLOW` as slow, the rest as medium.
+const FAST: Duration = Duration::from_millis(200);
+const SLOW: Duration = Duration::from_millis(500);
+
+/// Which API a request went to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Api {
+    Mojang,
+    Hypixel,
+}
+
+/// How many of one API's requests were fast, medium or slow.
+#[derive(Default)]
+struct LatencyBuckets {
+    /// Set once the first request has been logged.
+    connected: AtomicBool,
+    fast: AtomicU64,
+    medium: AtomicU64,
+    slow: AtomicU64,
+}
+
+impl LatencyBuckets {
+    fn record(&self, latency: Duration) {
+        let bucket = if latency < FAST {
+            &self.fast
+        } else if latency <= SLOW {
+            &self.medium
+        } else {
+            &self.slow
+        };
+        bucket.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "{} fast, {} medium, {} slow",
+            self.fast.load(Ordering::Relaxed),
+            self.medium.load(Ordering::Relaxed),
+            self.slow.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// What the overlay did this session, summarized on CTRL+C.
+#[derive(Default)]
+pub struct SessionMetrics {
+    who_events: AtomicU64,
+    players: Mutex<HashSet<Uuid>>,
+    mojang_calls: AtomicU64,
+    hypixel_calls: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    expired_evictions: AtomicU64,
+    capacity_evictions: AtomicU64,
+    api_errors: AtomicU64,
+    lookups: AtomicU64,
+    lookup_time_ms: AtomicU64,
+    mojang_latency: LatencyBuckets,
+    hypixel_latency: LatencyBuckets,
+    /// The Hypixel region last connected to, for telling apart slow API calls from a far away
+    /// server.
+    region: Mutex<Option<HypixelRegion>>,
+}
+
+impl SessionMetrics {
+    pub fn who_event(&self) {
+        self.who_events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn mojang_call(&self) {
+        self.mojang_calls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn hypixel_call(&self) {
+        self.hypixel_calls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn api_error(&self) {
+        self.api_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records how long a request to `api` took to answer. The first one per API is logged with
+    /// the address it connected to, since its time includes setting up the connection.
+    pub fn request_completed(&self, api: Api, remote: Option<SocketAddr>, latency: Duration) {
+        let buckets = match api {
+            Api::Mojang => &self.mojang_latency,
+            Api::Hypixel => &self.hypixel_latency,
+        };
+        if !buckets.connected.swap(true, Ordering::Relaxed) {
+            let remote = remote.map_or("an unknown address".to_string(), |addr| addr.to_string());
+            info!(
+                "First {api:?} API request went to {remote} and took {} ms, connection included",
+                latency.as_millis()
+            );
+        }
+        buckets.record(latency);
+    }
+
+    /// Records a lobby where `hits` players were shown from the cache and `misses` were fetched.
+    pub fn cache_lookup(&self, hits: usize, misses: usize) {
+        self.cache_hits.fetch_add(hits as u64, Ordering::Relaxed);
+        self.cache_misses
+            .fetch_add(misses as u64, Ordering::Relaxed);
+    }
+
+    pub fn cache_eviction(&self, reason: EvictionReason) {
+        let counter = match reason {
+            EvictionReason::Expired => &self.expired_evictions,
+            EvictionReason::CapacityExceeded => &self.capacity_evictions,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_region(&self, region: HypixelRegion) {
+        *self.region.lock().unwrap() = Some(region);
+    }
+
+    /// Records a successful stats fetch for `uuid` that took `latency`.
+    pub fn player_looked_up(&self, uuid: &Uuid, latency: Duration) {
+        self.players.lock().unwrap().insert(uuid.clone());
+        self.lookups.fetch_add(1, Ordering::Relaxed);
+        self.lookup_time_ms
+            .fetch_add(latency.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// The multi-line block logged at shutdown.
+    pub fn summary(&self) -> String {
+        let hits = self.cache_hits.load(Ordering::Relaxed);
+        let misses = self.cache_misses.load(Ordering::Relaxed);
+        let hit_rate = match hits + misses {
+            0 => 0.0,
+            total => hits as f64 / total as f64 * 100.0,
+        };
+        let lookups = self.lookups.load(Ordering::Relaxed);
+        let average_ms = match lookups {
+            0 => 0,
+            lookups => self.lookup_time_ms.load(Ordering::Relaxed) / lookups,
+        };
+
+        format!(
+            "Session summary:\n  \
+             /who events:        {}\n  \
+             Players looked up:  {}\n  \
+             Mojang API calls:   {}\n  \
+             Hypixel API calls:  {}\n  \
+             Cache hits/misses:  {hits}/{misses} ({hit_rate:.0}% hit rate)\n  \
+             Cache evictions:    {} expired, {} over capacity\n  \
+             API errors:         {}\n  \
+             Average lookup:     {average_ms} ms\n  \
+             Mojang latency:     {}\n  \
+             Hypixel latency:    {}\n  \
+             Hypixel region:     {}\n  \
+             (fast < {}ms, slow > {}ms)",
+            self.who_events.load(Ordering::Relaxed),
+            self.players.lock().unwrap().len(),
+            self.mojang_calls.load(Ordering::Relaxed),
+            self.hypixel_calls.load(Ordering::Relaxed),
+            self.expired_evictions.load(Ordering::Relaxed),
+            self.capacity_evictions.load(Ordering::Relaxed),
+            self.api_errors.load(Ordering::Relaxed),
+            self.mojang_latency.summary(),
+            self.hypixel_latency.summary(),
+            self.region
+                .lock()
+                .unwrap()
+                .map_or("not detected".to_string(), |region| region.to_string()),
+            FAST.as_millis(),
+            SLOW.as_millis(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_counts_unique_players_and_averages_latency() {
+        let metrics = SessionMetrics::default();
+        metrics.who_event();
+        metrics.cache_lookup(1, 3);
+        metrics.player_looked_up(&"a".to_string(), Duration::from_millis(100));
+        metrics.player_looked_up(&"a".to_string(), Duration::from_millis(300));
+        metrics.player_looked_up(&"b".to_string(), Duration::from_millis(200));
+
+        let summary = metrics.summary();
+
+        assert!(summary.contains("/who events:        1\n"));
+        assert!(summary.contains("Players looked up:  2\n"));
+        assert!(summary.contains("Cache hits/misses:  1/3 (25% hit rate)\n"));
+        assert!(summary.contains("Average lookup:     200 ms\n"));
+    }
+
+    #[test]
+    fn requests_are_bucketed_by_latency() {
+        let metrics = SessionMetrics::default();
+        for millis in [50, 199, 200, 500, 501] {
+            metrics.request_completed(Api::Hypixel, None, Duration::from_millis(millis));
+        }
+        metrics.request_completed(Api::Mojang, None, Duration::from_millis(900));
+
+        let summary = metrics.summary();
+
+        assert!(summary.contains("Mojang latency:     0 fast, 0 medium, 1 slow\n"));
+        assert!(summary.contains("Hypixel latency:    2 fast, 2 medium, 1 slow\n"));
+    }
+}
@@ -0,0 +1,188 @@
+use log::info;
+use regex::Regex;
+use std::{
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    sync::LazyLock,
+};
+
+/// How many lines from the top of the log are searched, the version is always logged early.
+const HEADER_LINES: usize = 200;
+
+/// Lines that name the game version: the System Details block, Fabric's loader banner, old
+/// Forge's FML banner and ModLauncher's arguments.
+static VERSION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?:Minecraft Version: |Loading Minecraft |for Minecraft |--fml\.mcVersion, |Minecraft )(\d+)\.(\d+)",
+    )
+    .unwrap()
+});
+
+/// The flavor of `latest.log`, which decides the thread names and prefixes in front of chat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLineFormat {
+    /// 1.8 to 1.15, chat on `[Client thread/INFO]`.
+    Vanilla112,
+    /// 1.16 to 1.19, chat on `[Render thread/INFO]`.
+    Vanilla116,
+    /// 1.20 and later, chat prefixed with `[System]`.
+    Vanilla120,
+    Fabric,
+    Forge,
+}
+
+impl LogLineFormat {
+    /// The thread name, as a regex, the client logs its own lines such as `Connecting to` on.
+    pub fn client_thread(self) -> &'static str {
+        match self {
+            LogLineFormat::Vanilla112 => r"Client thread",
+            LogLineFormat::Vanilla116 | LogLineFormat::Vanilla120 | LogLineFormat::Fabric => {
+                r"Render thread"
+            }
+            // FML banners are 1.8 to 1.12, ModLauncher is 1.13 and later
+            LogLineFormat::Forge => r"(?:Client|Render) thread",
+        }
+    }
+
+    /// What comes before every chat message, as a regex.
+    pub fn chat_prefix(self) -> &'static str {
+        match self {
+            LogLineFormat::Vanilla112 | LogLineFormat::Vanilla116 => r"\[CHAT\] ",
+            LogLineFormat::Vanilla120 => r"\[System\] \[CHAT\] ",
+            // Mod loaders span versions from before and after `[System]` was added
+            LogLineFormat::Fabric | LogLineFormat::Forge => r"(?:\[System\] )?\[CHAT\] ",
+        }
+    }
+}
+
+impl fmt::Display for LogLineFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogLineFormat::Vanilla112 => "vanilla 1.8-1.15",
+            LogLineFormat::Vanilla116 => "vanilla 1.16-1.19",
+            LogLineFormat::Vanilla120 => "vanilla 1.20+",
+            LogLineFormat::Fabric => "Fabric",
+            LogLineFormat::Forge => "Forge",
+        })
+    }
+}
+
+/// The `(major, minor)` Minecraft version named in the log header, e.g. `(1, 8)` for 1.8.9.
+pub fn detect_minecraft_version(log_content: &str) -> Option<(u8, u8)> {
+    log_content.lines().take(HEADER_LINES).find_map(|line| {
+        let captures = VERSION.captures(line)?;
+        Some((captures[1].parse().ok()?, captures[2].parse().ok()?))
+    })
+}
+
+/// Picks the log format from the header: a mod loader's banner wins, otherwise the version.
+pub fn detect_log_format(log_content: &str) -> Option<LogLineFormat> {
+    let header: Vec<&str> = log_content.lines().take(HEADER_LINES).collect();
+    if header.iter().any(|line| line.contains("Fabric Loader")) {
+        return Some(LogLineFormat::Fabric);
+    }
+    if header
+        .iter()
+        .any(|line| line.contains("[FML]") || line.contains("ModLauncher"))
+    {
+        return Some(LogLineFormat::Forge);
+    }
+
+    Some(match detect_minecraft_version(log_content)? {
+        (1, ..=15) => LogLineFormat::Vanilla112,
+        (1, 16..=19) => LogLineFormat::Vanilla116,
+        _ => LogLineFormat::Vanilla120,
+    })
+}
+
+/// Reads the header of the log at `log_path`, logs what it was written by and returns the format
+/// to build its [`LogPatterns`](crate::patterns::LogPatterns) for.
+pub fn detect_log_file_format(log_path: &Path) -> Option<LogLineFormat> {
+    let Ok(file) = File::open(log_path) else {
+        return None;
+    };
+    let header: String = BufReader::new(file)
+        .lines()
+        .take(HEADER_LINES)
+        .map_while(Result::ok)
+        .map(|line| line + "\n")
+        .collect();
+
+    let format = detect_log_format(&header);
+    match (format, detect_minecraft_version(&header)) {
+        (Some(format), Some((major, minor))) => info!(
+            "Detected {format} log format, Minecraft {major}.{minor}, in {}",
+            log_path.display()
+        ),
+        (Some(format), None) => info!("Detected {format} log format in {}", log_path.display()),
+        _ => info!(
+            "Could not detect the Minecraft version of {}, accepting every log format",
+            log_path.display()
+        ),
+    }
+    format
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns::LogPatterns;
+
+    #[test]
+    fn versions_and_formats_from_log_headers() {
+        let forge = "[12:00:00] [main/INFO] [LaunchWrapper]: Loading tweak class name net.minecraftforge.fml.common.launcher.FMLTweaker\n\
+                     [12:00:01] [main/INFO] [FML]: Forge Mod Loader version 11.15.1.2318 for Minecraft 1.8.9 loading\n";
+        assert_eq!(detect_minecraft_version(forge), Some((1, 8)));
+        assert_eq!(detect_log_format(forge), Some(LogLineFormat::Forge));
+
+        let fabric =
+            "[12:00:00] [main/INFO]: Loading Minecraft 1.20.1 with Fabric Loader 0.14.21\n";
+        assert_eq!(detect_minecraft_version(fabric), Some((1, 20)));
+        assert_eq!(detect_log_format(fabric), Some(LogLineFormat::Fabric));
+
+        let vanilla = "[12:00:00] [main/INFO]: -- System Details --\n\
+                       [12:00:00] [main/INFO]: \tMinecraft Version: 1.16.5\n";
+        assert_eq!(detect_log_format(vanilla), Some(LogLineFormat::Vanilla116));
+        assert_eq!(
+            detect_log_format("[12:00:00] [main/INFO]: Minecraft 1.12.2\n"),
+            Some(LogLineFormat::Vanilla112)
+        );
+        assert_eq!(
+            detect_log_format("[12:00:00] [Render thread/INFO]: Minecraft Version: 1.21\n"),
+            Some(LogLineFormat::Vanilla120)
+        );
+
+        let unknown = "[12:00:00] [main/INFO]: Setting user: Steve\n";
+        assert_eq!(detect_minecraft_version(unknown), None);
+        assert_eq!(detect_log_format(unknown), None);
+    }
+
+    #[test]
+    fn patterns_follow_the_detected_format() {
+        let countdown = r"\[CHAT\] The game starts in \d+ seconds?!";
+        let legacy = LogPatterns::new(Some(LogLineFormat::Vanilla112), countdown).unwrap();
+        let modern = LogPatterns::new(Some(LogLineFormat::Vanilla120), countdown).unwrap();
+        let unknown = LogPatterns::new(None, countdown).unwrap();
+
+        let client = "[12:00:00] [Client thread/INFO]: Connecting to mc.hypixel.net, 25565";
+        let render = "[12:00:00] [Render thread/INFO]: Connecting to mc.hypixel.net, 25565";
+        assert!(legacy.detect_server(client).is_some());
+        assert!(legacy.detect_server(render).is_none());
+        assert!(modern.detect_server(client).is_none());
+        assert!(modern.detect_server(render).is_some());
+        assert!(unknown.detect_server(client).is_some());
+        assert!(unknown.detect_server(render).is_some());
+
+        let system_chat =
+            "[12:00:00] [Render thread/INFO]: [System] [CHAT] Steve was killed by Alex. FINAL KILL!";
+        let bare_chat =
+            "[12:00:00] [Client thread/INFO]: [CHAT] Steve was killed by Alex. FINAL KILL!";
+        assert_eq!(modern.detect_final_kill(system_chat), Some("Steve"));
+        assert_eq!(modern.detect_final_kill(bare_chat), None);
+        assert_eq!(legacy.detect_final_kill(bare_chat), Some("Steve"));
+        assert_eq!(unknown.detect_final_kill(system_chat), Some("Steve"));
+        assert_eq!(unknown.detect_final_kill(bare_chat), Some("Steve"));
+    }
+}
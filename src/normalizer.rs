@@ -0,0 +1,55 @@
+use crate::hypixel::HypixelPlayer;
+
+/// Display columns that are only shown when listed in `display.columns`.
+pub const OPTIONAL_COLUMNS: [&str; 1] = ["norm_fkdr"];
+
+/// Stats relative to the reference player, above 1.0 where the player is better.
+#[derive(Debug, PartialEq)]
+pub struct NormalizedPlayer {
+    /// `None` when either FKDR is unknown or the reference has none to compare against.
+    pub fkdr: Option<f32>,
+}
+
+/// Compares players against a fixed reference player, usually the user themselves.
+pub struct StatNormalizer {
+    reference: HypixelPlayer,
+}
+
+impl StatNormalizer {
+    pub fn new(reference: HypixelPlayer) -> Self {
+        StatNormalizer { reference }
+    }
+
+    pub fn normalize(&self, player: &HypixelPlayer) -> NormalizedPlayer {
+        let ratio = |stat: f32, reference: f32| {
+            (stat.is_finite() && stat >= 0.0 && reference.is_finite() && reference > 0.0)
+                .then(|| stat / reference)
+        };
+
+        NormalizedPlayer {
+            fkdr: ratio(player.fkdr, self.reference.fkdr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hypixel::HypixelPlayerBuilder;
+
+    #[test]
+    fn fkdr_relative_to_reference() {
+        let normalizer =
+            StatNormalizer::new(HypixelPlayerBuilder::new("Me", "uuid").fkdr(2.0).build());
+
+        let better = HypixelPlayerBuilder::new("Steve", "uuid").fkdr(3.0).build();
+        assert_eq!(normalizer.normalize(&better).fkdr, Some(1.5));
+
+        let unknown = HypixelPlayerBuilder::new("Alex", "uuid").fkdr(-1.0).build();
+        assert_eq!(normalizer.normalize(&unknown).fkdr, None);
+
+        let no_reference =
+            StatNormalizer::new(HypixelPlayerBuilder::new("Me", "uuid").fkdr(0.0).build());
+        assert_eq!(no_reference.normalize(&better).fkdr, None);
+    }
+}
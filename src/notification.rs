@@ -0,0 +1,199 @@
+use log::{info, warn};
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+use crate::{config::Thresholds, display, hypixel::HypixelPlayer, i18n::text, Uuid};
+
+const FKDR_ALERT: f32 = 5.0;
+const FKDR_HIGH_ALERT: f32 = 10.0;
+const INDEX_ALERT: f32 = 10_000.0;
+const INDEX_HIGH_ALERT: f32 = 50_000.0;
+/// This many games in a day usually means the player is padding their stats.
+const RECENT_GAMES_ALERT: i32 = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlertSeverity {
+    Low,
+    Medium,
+    High,
+}
+
+/// A lobby player worth pointing out, with the names of every check they tripped.
+#[derive(Debug)]
+pub struct PlayerAlert {
+    pub severity: AlertSeverity,
+    pub message: String,
+    pub player: HypixelPlayer,
+    pub triggered_by: Vec<&'static str>,
+}
+
+impl From<HypixelPlayer> for Option<PlayerAlert> {
+    fn from(player: HypixelPlayer) -> Self {
+        PlayerAlert::check(player, &Thresholds::default())
+    }
+}
+
+impl PlayerAlert {
+    /// Runs every check against `player`, returning `None` when nothing tripped. FKDR and index
+    /// checks are skipped for players with too few games for their ratios to mean anything.
+    pub fn check(player: HypixelPlayer, thresholds: &Thresholds) -> Option<Self> {
+        let mut triggered_by = Vec::new();
+        let mut severity = AlertSeverity::Low;
+        let confidence_threshold = display::confidence_threshold();
+        let trusted = player.ratios_trusted(None, confidence_threshold);
+
+        if player.is_nicked() {
+            triggered_by.push("nick");
+        }
+        if trusted && player.fkdr >= FKDR_ALERT {
+            triggered_by.push("fkdr");
+            severity = severity.max(AlertSeverity::Medium);
+        }
+        if trusted && player.index_in(None) >= INDEX_ALERT {
+            triggered_by.push("index");
+            severity = severity.max(AlertSeverity::Medium);
+        }
+        if player.is_smurf_candidate(thresholds.karma_alert_threshold, confidence_threshold) {
+            triggered_by.push("smurf");
+            severity = severity.max(AlertSeverity::Medium);
+        }
+
+        if player.games_last_24h >= RECENT_GAMES_ALERT {
+            triggered_by.push("recent");
+            severity = severity.max(AlertSeverity::Medium);
+        }
+
+        if triggered_by.is_empty() {
+            return None;
+        }
+        if trusted && (player.fkdr >= FKDR_HIGH_ALERT || player.index_in(None) >= INDEX_HIGH_ALERT)
+            || triggered_by.len() > 1
+        {
+            severity = AlertSeverity::High;
+        }
+
+        Some(PlayerAlert {
+            severity,
+            message: text("alert.tripped")
+                .replace("{player}", &player.display_name())
+                .replace("{checks}", &triggered_by.join(", ")),
+            player,
+            triggered_by,
+        })
+    }
+
+    /// Logs the alert at a level matching its severity; high alerts also notify the desktop.
+    pub fn dispatch(&self) {
+        match self.severity {
+            AlertSeverity::High => {
+                warn!("{}", self.message);
+                send(text("alert.high_threat"), &self.message);
+            }
+            AlertSeverity::Medium => warn!("{}", self.message),
+            AlertSeverity::Low => info!("{}", self.message),
+        }
+    }
+}
+
+/// Remembers who was alerted about in the current lobby, so typing `/who` again doesn't repeat
+/// the same alerts. Nicks have no UUID, so players are told apart by
+/// [`HypixelPlayer::identity`]. Cleared when a new game is joined.
+#[derive(Clone, Default)]
+pub struct AlertCooldown {
+    alerted_this_lobby: Arc<Mutex<HashSet<(Uuid, String)>>>,
+}
+
+impl AlertCooldown {
+    /// Whether `alert` is the first for its player since the last `reset`. Marks the player as
+    /// alerted either way.
+    pub fn first_in_lobby(&self, alert: &PlayerAlert) -> bool {
+        let (uuid, name) = alert.player.identity();
+        self.alerted_this_lobby
+            .lock()
+            .unwrap()
+            .insert((uuid.to_string(), name))
+    }
+
+    pub fn reset(&self) {
+        self.alerted_this_lobby.lock().unwrap().clear();
+    }
+}
+
+/// Shows a desktop notification when the `notifications` feature is enabled, and does nothing
+/// otherwise. Failures are only logged, a missing notifier must never stop the overlay.
+#[cfg(feature = "notifications")]
+pub fn send(title: &str, body: &str) {
+    use std::process::Command;
+
+    #[cfg(target_os = "linux")]
+    let result = Command::new("notify-send").arg(title).arg(body).status();
+    #[cfg(target_os = "macos")]
+    let result = Command::new("osascript")
+        .arg("-e")
+        .arg(format!(
+            "display notification {:?} with title {:?}",
+            body, title
+        ))
+        .status();
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    let result: std::io::Result<std::process::ExitStatus> = Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "no notifier for this platform",
+    ));
+
+    if let Err(e) = result {
+        log::warn!("Failed to send notification: {e}");
+    }
+}
+
+#[cfg(not(feature = "notifications"))]
+pub fn send(_title: &str, _body: &str) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hypixel::HypixelPlayerBuilder;
+
+    #[test]
+    fn players_are_alerted_about_once_per_lobby() {
+        let player = HypixelPlayerBuilder::new("Steve", "uuid").fkdr(8.0).build();
+        let alert = PlayerAlert::check(player, &Thresholds::default()).unwrap();
+        let cooldown = AlertCooldown::default();
+
+        assert!(cooldown.first_in_lobby(&alert));
+        assert!(!cooldown.first_in_lobby(&alert));
+
+        cooldown.reset();
+        assert!(cooldown.first_in_lobby(&alert));
+    }
+
+    #[test]
+    fn every_nick_in_a_lobby_is_alerted_about() {
+        let cooldown = AlertCooldown::default();
+        let nick = |name| PlayerAlert::check(HypixelPlayer::unknown(name), &Thresholds::default());
+        let (first, second) = (nick("Nick_One").unwrap(), nick("Nick_Two").unwrap());
+
+        assert!(cooldown.first_in_lobby(&first));
+        assert!(cooldown.first_in_lobby(&second));
+        assert!(!cooldown.first_in_lobby(&nick("nick_two").unwrap()));
+    }
+
+    #[test]
+    fn ratios_from_few_games_raise_no_alert() {
+        let outlier = HypixelPlayerBuilder::new("Steve", "uuid")
+            .level(2)
+            .fkdr(10.0)
+            .confidence(0.02)
+            .build();
+        assert!(PlayerAlert::check(outlier.clone(), &Thresholds::default()).is_none());
+
+        let proven = HypixelPlayer {
+            confidence: 1.0,
+            ..outlier
+        };
+        let alert = PlayerAlert::check(proven, &Thresholds::default()).unwrap();
+        assert_eq!(alert.triggered_by, ["fkdr", "smurf"]);
+    }
+}
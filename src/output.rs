@@ -0,0 +1,156 @@
+use anyhow::{Context, Result};
+use std::{future::Future, path::PathBuf, pin::Pin};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::Mutex,
+};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Where player stats are written. Boxed futures keep the trait usable as `dyn OutputSink`.
+pub trait OutputSink: Send + Sync {
+    /// Writes `data` followed by a newline.
+    fn write<'a>(&'a self, data: &'a str) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Parsed form of the `output-sink` config value.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SinkSpec {
+    Stdout,
+    File(PathBuf),
+    Fifo(PathBuf),
+}
+
+impl SinkSpec {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        if spec == "stdout" {
+            return Ok(SinkSpec::Stdout);
+        }
+
+        let Some((kind, target)) = spec.split_once(':') else {
+            return Err(format!(
+                "output-sink {spec:?} must be stdout, file:<path> or fifo:<path>"
+            ));
+        };
+        if target.is_empty() {
+            return Err(format!(
+                "output-sink {spec:?} is missing a target after {kind}:"
+            ));
+        }
+
+        match kind {
+            "file" => Ok(SinkSpec::File(PathBuf::from(target))),
+            "fifo" => Ok(SinkSpec::Fifo(PathBuf::from(target))),
+            _ => Err(format!("output-sink kind {kind:?} is not supported")),
+        }
+    }
+}
+
+/// Opens the sink described by the `output-sink` config value.
+pub async fn from_config(spec: &str) -> Result<Box<dyn OutputSink>> {
+    match SinkSpec::parse(spec).map_err(anyhow::Error::msg)? {
+        SinkSpec::Stdout => Ok(Box::new(StdoutSink)),
+        SinkSpec::File(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .await
+                .with_context(|| format!("Failed to open output file {}", path.display()))?;
+            Ok(Box::new(FileSink {
+                file: Mutex::new(file),
+            }))
+        }
+        SinkSpec::Fifo(path) => {
+            if !path.exists() {
+                anyhow::bail!(
+                    "Named pipe {} does not exist, create it with mkfifo",
+                    path.display()
+                );
+            }
+            Ok(Box::new(FifoSink {
+                path,
+                pipe: Mutex::new(None),
+            }))
+        }
+    }
+}
+
+struct StdoutSink;
+
+impl OutputSink for StdoutSink {
+    fn write<'a>(&'a self, data: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            println!("{data}");
+            Ok(())
+        })
+    }
+}
+
+/// Appends to a regular file.
+struct FileSink {
+    file: Mutex<File>,
+}
+
+impl OutputSink for FileSink {
+    fn write<'a>(&'a self, data: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut file = self.file.lock().await;
+            file.write_all(format!("{data}\n").as_bytes())
+                .await
+                .context("Failed to write to the output file")?;
+            file.flush()
+                .await
+                .context("Failed to flush the output file")
+        })
+    }
+}
+
+/// Writes to a named pipe. The pipe is opened on first write, which waits for a reader, and
+/// reopened after the reader goes away.
+struct FifoSink {
+    path: PathBuf,
+    pipe: Mutex<Option<File>>,
+}
+
+impl OutputSink for FifoSink {
+    fn write<'a>(&'a self, data: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut pipe = self.pipe.lock().await;
+            if pipe.is_none() {
+                let file = OpenOptions::new()
+                    .write(true)
+                    .open(&self.path)
+                    .await
+                    .with_context(|| format!("Failed to open {}", self.path.display()))?;
+                *pipe = Some(file);
+            }
+
+            let result = match pipe.as_mut() {
+                Some(file) => file.write_all(format!("{data}\n").as_bytes()).await,
+                None => Ok(()),
+            };
+            if result.is_err() {
+                *pipe = None;
+            }
+            result.with_context(|| format!("Failed to write to {}", self.path.display()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sink_specs() {
+        assert_eq!(SinkSpec::parse("stdout"), Ok(SinkSpec::Stdout));
+        assert_eq!(
+            SinkSpec::parse("file:stats.txt"),
+            Ok(SinkSpec::File(PathBuf::from("stats.txt")))
+        );
+        assert!(SinkSpec::parse("fifo:").is_err());
+        assert!(SinkSpec::parse("ws:127.0.0.1:9000").is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+
+use crate::{
+    hypixel::BedwarsMode, minecraft_version::LogLineFormat, server::Server, team::TeamColor,
+};
+
+/// Used when the log's format couldn't be detected, accepts what any of the formats write.
+const ANY_CLIENT_THREAD: &str = r"(?:Client|Render) thread";
+const ANY_CHAT_PREFIX: &str = r"(?:\[System\] )?\[CHAT\] ";
+
+/// Every chat pattern the overlay reacts to, compiled once at startup.
+pub struct LogPatterns {
+    pub online: Regex,
+    online_continuation: Regex,
+    online_complete: Regex,
+    mode: Regex,
+    bed_destruction: Regex,
+    final_kill: Regex,
+    team_chat: Regex,
+    lobby_countdown: Regex,
+    server_connect: Regex,
+}
+
+impl LogPatterns {
+    /// Builds the patterns for a log in `format`, or for any format if it wasn't detected.
+    pub fn new(format: Option<LogLineFormat>, lobby_countdown_pattern: &str) -> Result<Self> {
+        let (thread, chat) = format.map_or((ANY_CLIENT_THREAD, ANY_CHAT_PREFIX), |format| {
+            (format.client_thread(), format.chat_prefix())
+        });
+        Ok(LogPatterns {
+            online: Regex::new(&format!(r"{chat}ONLINE: (.*)")).unwrap(),
+            online_continuation: Regex::new(&format!(
+                r"{chat}([A-Za-z0-9_]{{1,16}}(?:, ?[A-Za-z0-9_]{{1,16}})*,?)\s*$"
+            ))
+            .unwrap(),
+            online_complete: Regex::new(r"[A-Za-z0-9_]{3,16}$").unwrap(),
+            mode: Regex::new(&format!(
+                r#"{chat}(?:\{{.*"mode":"(BEDWARS_\w+)".*\}}|You are in Bed Wars (\w+))"#
+            ))
+            .unwrap(),
+            bed_destruction: Regex::new(&format!(
+                r"{chat}BED DESTRUCTION > (\w+) [Bb]ed was \w+ by (\w{{1,16}})"
+            ))
+            .unwrap(),
+            final_kill: Regex::new(&format!(r"{chat}(\w{{1,16}}) .*FINAL KILL!")).unwrap(),
+            // Upper case, so ranks like `[VIP]` are never taken for a team
+            team_chat: Regex::new(&format!(
+                r"{chat}(?:\[[^\]]*\] )*?\[(RED|BLUE|GREEN|YELLOW|AQUA|WHITE|PINK|GRAY)\] (?:\[[^\]]*\] )*(\w{{1,16}}): "
+            ))
+            .unwrap(),
+            lobby_countdown: Regex::new(lobby_countdown_pattern)
+                .context("Invalid lobby-countdown-pattern")?,
+            // The client's own log line, never chat, so players can't fake a server switch
+            server_connect: Regex::new(&format!(
+                r"\[{thread}/INFO\]: Connecting to ([^,\s]+), ?\d+\s*$"
+            ))
+            .unwrap(),
+        })
+    }
+
+    /// Detects the Bedwars mode from a `/locraw` response or a mode announcement.
+    pub fn detect_mode(&self, line: &str) -> Option<BedwarsMode> {
+        let captures = self.mode.captures(line)?;
+        let mode = captures.get(1).or_else(|| captures.get(2))?;
+        BedwarsMode::from_chat(mode.as_str())
+    }
+
+    /// Whether an `ONLINE:` list was cut off, i.e. doesn't end with a whole player name.
+    pub fn online_truncated(&self, names: &str) -> bool {
+        !self.online_complete.is_match(names.trim_end())
+    }
+
+    /// Returns the names on a chat line that holds nothing but a comma separated list of player
+    /// names, the shape of the lines a wrapped `ONLINE:` list continues on.
+    pub fn detect_online_continuation<'a>(&self, line: &'a str) -> Option<&'a str> {
+        Some(self.online_continuation.captures(line)?.get(1)?.as_str())
+    }
+
+    /// Returns the team whose bed was destroyed and the player who destroyed it.
+    pub fn detect_bed_destruction<'a>(&self, line: &'a str) -> Option<(&'a str, &'a str)> {
+        let captures = self.bed_destruction.captures(line)?;
+        Some((captures.get(1)?.as_str(), captures.get(2)?.as_str()))
+    }
+
+    /// Returns the name of the player who was final killed.
+    pub fn detect_final_kill<'a>(&self, line: &'a str) -> Option<&'a str> {
+        Some(self.final_kill.captures(line)?.get(1)?.as_str())
+    }
+
+    /// Returns the team and name of an in-game chat message's sender, e.g. from
+    /// `[SHOUT] [RED] [MVP+] Steve: gl`.
+    pub fn detect_team_member<'a>(&self, line: &'a str) -> Option<(TeamColor, &'a str)> {
+        let captures = self.team_chat.captures(line)?;
+        Some((
+            TeamColor::from_name(captures.get(1)?.as_str())?,
+            captures.get(2)?.as_str(),
+        ))
+    }
+
+    /// Whether the line is the pre-game countdown, e.g. `The game starts in 5 seconds!`.
+    pub fn detect_lobby_countdown(&self, line: &str) -> bool {
+        self.lobby_countdown.is_match(line)
+    }
+
+    /// Returns the server the client logged connecting to, e.g. in
+    /// `[Client thread/INFO]: Connecting to mc.hypixel.net, 25565`.
+    pub fn detect_server(&self, line: &str) -> Option<Server> {
+        let captures = self.server_connect.captures(line)?;
+        Some(Server::from_host(captures.get(1)?.as_str()))
+    }
+}
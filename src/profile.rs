@@ -0,0 +1,152 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use std::{fs, path::PathBuf};
+
+use crate::{
+    cli::ProfileCommand,
+    config::{normalize_config, validate_config, Config},
+};
+
+pub const PROFILES_DIR: &str = "profiles";
+
+const PROFILE_TEMPLATE: &str =
+    "# Keys set here override config.toml, e.g.\n# api-key = \"...\"\n# log-path = \"...\"\n";
+
+/// Named config overrides stored as `profiles/<name>.toml`, for switching between accounts or
+/// Minecraft installs without editing `config.toml`.
+pub struct ProfileManager {
+    dir: PathBuf,
+}
+
+impl ProfileManager {
+    pub fn new() -> Self {
+        ProfileManager {
+            dir: PathBuf::from(PROFILES_DIR),
+        }
+    }
+
+    /// Profile names become file names, so only plain names are allowed.
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            anyhow::bail!("Invalid profile name {name:?}, use letters, digits, - and _ only");
+        }
+
+        Ok(self.dir.join(format!("{name}.toml")))
+    }
+
+    /// Names of every profile, sorted.
+    pub fn list(&self) -> Result<Vec<String>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut names: Vec<String> = fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read {}", self.dir.display()))?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "toml" {
+                    return None;
+                }
+                Some(path.file_stem()?.to_string_lossy().to_string())
+            })
+            .collect();
+        names.sort();
+
+        Ok(names)
+    }
+
+    /// Creates an empty profile, which falls through to `config.toml` for every key.
+    pub fn create(&self, name: &str) -> Result<PathBuf> {
+        let path = self.path(name)?;
+        if path.exists() {
+            anyhow::bail!("Profile {name} already exists at {}", path.display());
+        }
+
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        fs::write(&path, PROFILE_TEMPLATE)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+
+        Ok(path)
+    }
+
+    /// Layers the keys set in profile `name` over `base`.
+    pub fn load(&self, name: &str, base: &Config) -> Result<Config> {
+        let path = self.path(name)?;
+        let profile_str = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read profile {}", path.display()))?;
+        let profile: toml::Table = toml::from_str(&profile_str)
+            .with_context(|| format!("Failed to parse profile {}", path.display()))?;
+
+        let mut merged =
+            toml::Table::try_from(base).context("Failed to serialize the base config")?;
+        merged.extend(profile);
+        let mut config: Config = merged
+            .try_into()
+            .with_context(|| format!("Invalid config in profile {}", path.display()))?;
+        normalize_config(&mut config);
+
+        Ok(config)
+    }
+
+    /// Problems with the merged config of profile `name`, like `validate_config`.
+    pub fn validate(&self, name: &str, base: &Config) -> Result<Vec<String>> {
+        Ok(validate_config(&self.load(name, base)?))
+    }
+
+    /// Loads profile `name` and marks the process with it, so several overlays can be told apart.
+    pub fn activate(&self, name: &str, base: &Config) -> Result<Config> {
+        let config = self.load(name, base)?;
+        for problem in validate_config(&config) {
+            warn!("Profile {name} problem: {problem}");
+        }
+        set_process_title(&format!("bwoverlay-{name}"));
+        info!("Using profile {name}");
+
+        Ok(config)
+    }
+
+    /// Runs a `bwoverlay profile ...` subcommand, printing its result.
+    pub fn run_command(&self, command: ProfileCommand, base: &Config) -> Result<()> {
+        match command {
+            ProfileCommand::List => {
+                let names = self.list()?;
+                if names.is_empty() {
+                    println!("No profiles in {}", self.dir.display());
+                }
+                for name in names {
+                    match self.validate(&name, base) {
+                        Ok(problems) if problems.is_empty() => println!("{name}"),
+                        Ok(problems) => println!("{name} ({})", problems.join("; ")),
+                        Err(e) => println!("{name} (unusable: {e:#})"),
+                    }
+                }
+            }
+            ProfileCommand::Create(name) => {
+                let path = self.create(&name)?;
+                println!("Created profile {name} at {}", path.display());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Linux truncates the name to 15 bytes.
+#[cfg(target_os = "linux")]
+fn set_process_title(title: &str) {
+    let Ok(title) = std::ffi::CString::new(title) else {
+        return;
+    };
+    // SAFETY: PR_SET_NAME only reads a NUL-terminated string, which `title` is.
+    unsafe {
+        libc::prctl(libc::PR_SET_NAME, title.as_ptr());
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_process_title(_title: &str) {}
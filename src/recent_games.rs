@@ -0,0 +1,123 @@
+use anyhow::{Context, Result};
+use log::error;
+use serde_derive::Deserialize;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{api::HypixelApiClient, Uuid};
+
+/// Recent games change every few minutes, so they are cached far more briefly than stats.
+const RECENT_GAMES_TTL: Duration = Duration::from_secs(15 * 60);
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Deserialize)]
+struct ApiRecentGames {
+    games: Vec<RecentGame>,
+}
+
+/// One of the last 100 games Hypixel recorded for a player. Timestamps are epoch milliseconds.
+#[allow(dead_code)]
+#[derive(Deserialize, Debug)]
+pub struct RecentGame {
+    #[serde(default)]
+    pub mode: String,
+    pub date: i64,
+    pub ended: Option<i64>,
+}
+
+pub async fn get_recent_games(uuid: &Uuid, client: &HypixelApiClient) -> Result<Vec<RecentGame>> {
+    let body = client.get("v2/recentgames", uuid).await?;
+    let recent: ApiRecentGames = serde_json::from_str(&body)
+        .with_context(|| format!("Failed to parse recent games for {uuid}"))?;
+
+    Ok(recent.games)
+}
+
+fn count_last_24h(games: &[RecentGame]) -> i32 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+
+    games
+        .iter()
+        .filter(|game| game.date > now - DAY_MILLIS)
+        .count() as i32
+}
+
+/// Returns how many games `uuid` played in the last 24 hours, or -1 if they could not be
+/// fetched, matching how other unknown stats are shown.
+pub async fn fetch_games_last_24h(uuid: &Uuid, client: &HypixelApiClient) -> i32 {
+    match get_recent_games(uuid, client).await {
+        Ok(games) => count_last_24h(&games),
+        Err(e) => {
+            error!("Error while getting recent games: {e}");
+            -1
+        }
+    }
+}
+
+/// Games played in the last 24 hours per player, refetched once `RECENT_GAMES_TTL` has passed.
+/// Only holds counts, fetching is left to the caller so the cache is never locked across a
+/// request.
+#[derive(Default)]
+pub struct RecentGamesCache {
+    entries: HashMap<Uuid, (i32, Instant)>,
+}
+
+impl RecentGamesCache {
+    /// The count for `uuid`, unless it was never fetched or is older than `RECENT_GAMES_TTL`.
+    pub fn cached(&self, uuid: &Uuid) -> Option<i32> {
+        let (count, fetched_at) = self.entries.get(uuid)?;
+        (fetched_at.elapsed() <= RECENT_GAMES_TTL).then_some(*count)
+    }
+
+    /// Caches a fetched count. Failed fetches (-1) aren't cached, so they are retried.
+    pub fn insert(&mut self, uuid: Uuid, count: i32) {
+        if count >= 0 {
+            self.entries.insert(uuid, (count, Instant::now()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{api::ConnectionPool, test_helpers::MockHypixelServer};
+    use reqwest::Client;
+
+    #[tokio::test]
+    async fn recent_games_are_cached() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+        let server = MockHypixelServer::start().await;
+        server.register(
+            "v2/recentgames",
+            "uuid",
+            &format!(
+                r#"{{"success":true,"games":[{{"date":{}}},{{"date":{}}}]}}"#,
+                now - 60_000,
+                now - 2 * DAY_MILLIS
+            ),
+        );
+        let client =
+            HypixelApiClient::new("key".to_string(), ConnectionPool::shared(Client::new()))
+                .with_base_url(server.base_url());
+        let mut cache = RecentGamesCache::default();
+
+        let uuid = "uuid".to_string();
+        assert_eq!(cache.cached(&uuid), None);
+        let count = fetch_games_last_24h(&uuid, &client).await;
+        assert_eq!(count, 1);
+        cache.insert(uuid.clone(), count);
+        assert_eq!(cache.cached(&uuid), Some(1));
+        assert_eq!(server.request_count(), 1);
+
+        cache.insert("nick".to_string(), -1);
+        assert_eq!(cache.cached(&"nick".to_string()), None);
+    }
+}
@@ -0,0 +1,79 @@
+use std::fmt;
+
+/// Which of Hypixel's regions a hostname points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HypixelRegion {
+    NorthAmerica,
+    Europe,
+    Asia,
+    /// A Hypixel hostname that doesn't name a region.
+    Unknown,
+}
+
+impl fmt::Display for HypixelRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HypixelRegion::NorthAmerica => "North America",
+            HypixelRegion::Europe => "Europe",
+            HypixelRegion::Asia => "Asia",
+            HypixelRegion::Unknown => "unknown",
+        })
+    }
+}
+
+/// The server the client connected to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Server {
+    Hypixel(HypixelRegion),
+    /// Any other server, by hostname.
+    Other(String),
+}
+
+impl Server {
+    /// The server a hostname from the client's connect line points at.
+    pub fn from_host(host: &str) -> Self {
+        // Hostnames are case insensitive and may be written fully qualified, with a trailing dot
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+
+        let Some(subdomain) = host.strip_suffix("hypixel.net") else {
+            return Server::Other(host);
+        };
+        Server::Hypixel(match subdomain.trim_end_matches('.') {
+            "" | "mc" | "na" | "us" => HypixelRegion::NorthAmerica,
+            "eu" => HypixelRegion::Europe,
+            "asia" | "as" => HypixelRegion::Asia,
+            _ if subdomain.ends_with('.') => HypixelRegion::Unknown,
+            // Some other domain that merely ends in `hypixel.net`
+            _ => return Server::Other(host),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regions_from_hostnames() {
+        assert_eq!(
+            Server::from_host("mc.hypixel.net"),
+            Server::Hypixel(HypixelRegion::NorthAmerica)
+        );
+        assert_eq!(
+            Server::from_host("EU.Hypixel.net."),
+            Server::Hypixel(HypixelRegion::Europe)
+        );
+        assert_eq!(
+            Server::from_host("stuck.hypixel.net"),
+            Server::Hypixel(HypixelRegion::Unknown)
+        );
+        assert_eq!(
+            Server::from_host("nothypixel.net"),
+            Server::Other("nothypixel.net".to_string())
+        );
+        assert_eq!(
+            Server::from_host("localhost"),
+            Server::Other("localhost".to_string())
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::{Context, Result};
+use std::io::{self, BufRead, Write};
+
+use crate::config::{check_api_key, Config};
+
+/// Asks for the values a new config can't do without. Whatever isn't answered, for example when
+/// stdin closes, keeps its value from `config`.
+pub fn prompt_config(mut config: Config) -> Result<Config> {
+    println!("No {} found, let's set one up.", crate::config::CONFIG_PATH);
+
+    loop {
+        let Some(api_key) = prompt("Enter your Hypixel API key: ", true)? else {
+            return Ok(config);
+        };
+        match check_api_key(&api_key) {
+            Ok(()) => {
+                config.api_key = api_key;
+                break;
+            }
+            Err(problem) => println!("{problem}, try again."),
+        }
+    }
+
+    let question = format!(
+        "Enter your Minecraft log path [default: {}]: ",
+        config.log_path
+    );
+    if let Some(log_path) = prompt(&question, false)?.filter(|path| !path.is_empty()) {
+        config.log_path = log_path;
+    }
+
+    Ok(config)
+}
+
+/// Prints `question` and reads one trimmed line, `None` once stdin is closed.
+fn prompt(question: &str, hidden: bool) -> Result<Option<String>> {
+    print!("{question}");
+    io::stdout().flush().context("Failed to write the prompt")?;
+
+    let mut line = String::new();
+    let read = if hidden {
+        read_hidden_line(&mut line)
+    } else {
+        io::stdin().lock().read_line(&mut line)
+    }
+    .context("Failed to read from stdin")?;
+
+    Ok((read > 0).then(|| line.trim().to_string()))
+}
+
+/// Reads a line with terminal echo turned off, so the API key doesn't stay on screen.
+#[cfg(target_os = "linux")]
+fn read_hidden_line(line: &mut String) -> io::Result<usize> {
+    use std::os::fd::AsRawFd;
+
+    let fd = io::stdin().as_raw_fd();
+    let mut saved = std::mem::MaybeUninit::<libc::termios>::uninit();
+    // SAFETY: tcgetattr fills `saved` when it returns 0, which is checked before it is read.
+    let saved = unsafe {
+        if libc::tcgetattr(fd, saved.as_mut_ptr()) != 0 {
+            return io::stdin().lock().read_line(line);
+        }
+        saved.assume_init()
+    };
+
+    let mut hidden = saved;
+    hidden.c_lflag &= !libc::ECHO;
+    hidden.c_lflag |= libc::ECHONL;
+    // SAFETY: both calls only read the termios structs passed to them.
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &hidden) };
+    let read = io::stdin().lock().read_line(line);
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &saved) };
+
+    read
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_hidden_line(line: &mut String) -> io::Result<usize> {
+    log::warn!(
+        "Hiding input is not supported on this platform, the API key will be shown as typed"
+    );
+    io::stdin().lock().read_line(line)
+}
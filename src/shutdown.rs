@@ -0,0 +1,136 @@
+use log::{info, warn};
+use std::{future::Future, time::Duration};
+use tokio::{sync::oneshot, time::Instant};
+
+/// A subsystem's end of the shutdown sequence.
+pub struct ShutdownSignal {
+    name: &'static str,
+    signal: oneshot::Receiver<()>,
+    done: oneshot::Sender<()>,
+}
+
+impl ShutdownSignal {
+    /// Runs `cleanup` once shutdown reaches this subsystem, then lets the next one start.
+    pub fn on_shutdown(self, cleanup: impl Future<Output = ()> + Send + 'static) {
+        tokio::spawn(async move {
+            // The coordinator was dropped without shutting down
+            if self.signal.await.is_err() {
+                return;
+            }
+            cleanup.await;
+            info!("Shut down the {}", self.name);
+            let _ = self.done.send(());
+        });
+    }
+}
+
+struct Subsystem {
+    name: &'static str,
+    signal: oneshot::Sender<()>,
+    done: oneshot::Receiver<()>,
+}
+
+/// Cleans up subsystems one after another, in the order they were registered, so e.g. the log
+/// watcher stops before in-flight lookups are drained.
+pub struct ShutdownCoordinator {
+    timeout: Duration,
+    subsystems: Vec<Subsystem>,
+}
+
+impl ShutdownCoordinator {
+    pub fn new(timeout: Duration) -> Self {
+        ShutdownCoordinator {
+            timeout,
+            subsystems: Vec::new(),
+        }
+    }
+
+    pub fn register(&mut self, name: &'static str) -> ShutdownSignal {
+        let (signal_tx, signal_rx) = oneshot::channel();
+        let (done_tx, done_rx) = oneshot::channel();
+        self.subsystems.push(Subsystem {
+            name,
+            signal: signal_tx,
+            done: done_rx,
+        });
+
+        ShutdownSignal {
+            name,
+            signal: signal_rx,
+            done: done_tx,
+        }
+    }
+
+    /// Signals every subsystem in order, giving up on the rest once `timeout` has passed.
+    pub async fn shutdown(self) {
+        let deadline = Instant::now() + self.timeout;
+
+        for subsystem in self.subsystems {
+            if subsystem.signal.send(()).is_err() {
+                continue;
+            }
+            if tokio::time::timeout_at(deadline, subsystem.done)
+                .await
+                .is_err()
+            {
+                warn!(
+                    "Shutdown took longer than {}s while stopping the {}, exiting anyway",
+                    self.timeout.as_secs(),
+                    subsystem.name
+                );
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn record(
+        stopped: &Arc<Mutex<Vec<&'static str>>>,
+        name: &'static str,
+    ) -> impl Future<Output = ()> {
+        let stopped = Arc::clone(stopped);
+        async move { stopped.lock().unwrap().push(name) }
+    }
+
+    #[tokio::test]
+    async fn subsystems_stop_in_registration_order() {
+        let stopped = Arc::new(Mutex::new(Vec::new()));
+        let mut coordinator = ShutdownCoordinator::new(Duration::from_secs(5));
+        let watcher = coordinator.register("log watcher");
+        let lookups = coordinator.register("player lookups");
+        let history = coordinator.register("stat history");
+
+        // Cleanups attached out of order still run in the order they were registered
+        history.on_shutdown(record(&stopped, "stat history"));
+        lookups.on_shutdown(async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        });
+        watcher.on_shutdown(record(&stopped, "log watcher"));
+        coordinator.shutdown().await;
+
+        assert_eq!(*stopped.lock().unwrap(), ["log watcher", "stat history"]);
+    }
+
+    #[tokio::test]
+    async fn a_slow_subsystem_hits_the_timeout() {
+        let stopped = Arc::new(Mutex::new(Vec::new()));
+        let mut coordinator = ShutdownCoordinator::new(Duration::from_millis(100));
+        coordinator.register("player lookups").on_shutdown(async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        });
+        coordinator
+            .register("stat history")
+            .on_shutdown(record(&stopped, "stat history"));
+
+        let started = Instant::now();
+        coordinator.shutdown().await;
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(stopped.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,271 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{hypixel::HypixelPlayer, Uuid};
+
+pub const HISTORY_PATH: &str = "history.json";
+/// Compaction keeps at most this many records in `history.json`.
+pub const HISTORY_MAX_RECORDS: usize = 10_000;
+/// Records older than this are dropped by compaction.
+const HISTORY_MAX_AGE_SECS: i64 = 90 * 24 * 60 * 60;
+/// Records kept per player, enough for the FKDR trend.
+const RECORDS_PER_PLAYER: usize = 10;
+
+/// A player's stats at the moment they were looked up. `timestamp` is in epoch seconds. Ratios
+/// are `None` when unknown or undefined, e.g. FKDR with no final deaths, since JSON can't hold
+/// inf or NaN.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StatRecord {
+    pub uuid: Uuid,
+    pub timestamp: i64,
+    pub fkdr: Option<f32>,
+    pub wlr: Option<f32>,
+    pub level: i32,
+    pub finals: i32,
+    pub wins: i32,
+}
+
+/// Every lookup across sessions, keyed by UUID. `history.json` holds one record per line so
+/// new lookups can be appended without rewriting the file.
+pub struct StatHistory {
+    path: PathBuf,
+    records: HashMap<Uuid, Vec<StatRecord>>,
+}
+
+impl StatHistory {
+    /// Reads the history at `path`, starting empty if it doesn't exist yet. Lines that fail to
+    /// parse are skipped so one bad write doesn't lose the rest.
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let mut records: HashMap<Uuid, Vec<StatRecord>> = HashMap::new();
+
+        if path.exists() {
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+                match serde_json::from_str::<StatRecord>(line) {
+                    Ok(record) => records.entry(record.uuid.clone()).or_default().push(record),
+                    Err(e) => warn!("Skipping bad line in {}: {e}", path.display()),
+                }
+            }
+        }
+
+        Ok(StatHistory { path, records })
+    }
+
+    /// Records `player`'s current stats in memory and appends them to the history file.
+    pub fn record(&mut self, player: &HypixelPlayer) -> Result<()> {
+        let record = StatRecord {
+            uuid: player.uuid.clone(),
+            timestamp: now_secs(),
+            fkdr: known_ratio(player.fkdr),
+            wlr: known_ratio(player.wlr),
+            level: player.level,
+            finals: player.final_kills,
+            wins: player.wins,
+        };
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&record)?)
+            .with_context(|| format!("Failed to write to {}", self.path.display()))?;
+
+        self.records
+            .entry(record.uuid.clone())
+            .or_default()
+            .push(record);
+        Ok(())
+    }
+
+    /// Compacts the history file with `compact_cache` and reloads what is left.
+    pub fn compact(&mut self, max_entries: usize) -> Result<()> {
+        compact_cache(&self.path, max_entries)?;
+        *self = StatHistory::load(self.path.clone())?;
+        Ok(())
+    }
+
+    /// Least squares slope of the last `n` FKDR values against their lookup order. Positive means
+    /// the player is improving (or being boosted), negative means their FKDR is dropping.
+    /// `None` until at least two lookups exist.
+    pub fn fkdr_trend(&self, uuid: &Uuid, n: usize) -> Option<f32> {
+        let records = self.records.get(uuid)?;
+        let recent = &records[records.len().saturating_sub(n)..];
+        slope(&recent.iter().filter_map(|r| r.fkdr).collect::<Vec<_>>())
+    }
+}
+
+/// `ratio`, unless it is the unknown `-1`, inf or NaN.
+fn known_ratio(ratio: f32) -> Option<f32> {
+    (ratio.is_finite() && ratio >= 0.0).then_some(ratio)
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Shrinks the history at `cache_path`: drops lines that fail to parse, repeated records, records
+/// older than `HISTORY_MAX_AGE_SECS` and all but each player's latest `RECORDS_PER_PLAYER`, then
+/// the oldest records until at most `max_entries` are left. A missing file is left missing.
+pub fn compact_cache(cache_path: &Path, max_entries: usize) -> Result<()> {
+    if !cache_path.exists() {
+        return Ok(());
+    }
+    let contents = fs::read_to_string(cache_path)
+        .with_context(|| format!("Failed to read {}", cache_path.display()))?;
+    let lines: Vec<&str> = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let before = lines.len();
+
+    let oldest_kept = now_secs() - HISTORY_MAX_AGE_SECS;
+    let mut seen = HashSet::new();
+    let mut records: Vec<StatRecord> = lines
+        .iter()
+        .filter_map(|line| serde_json::from_str::<StatRecord>(line).ok())
+        .filter(|record| record.timestamp >= oldest_kept)
+        .filter(|record| seen.insert((record.uuid.clone(), record.timestamp)))
+        .collect();
+
+    // Newest first, so the records to keep come before the ones to drop
+    records.sort_by_key(|record| std::cmp::Reverse(record.timestamp));
+    let mut per_player: HashMap<Uuid, usize> = HashMap::new();
+    records.retain(|record| {
+        let count = per_player.entry(record.uuid.clone()).or_default();
+        *count += 1;
+        *count <= RECORDS_PER_PLAYER
+    });
+    records.truncate(max_entries);
+    records.reverse();
+
+    let mut compacted = String::new();
+    for record in &records {
+        compacted.push_str(&serde_json::to_string(record)?);
+        compacted.push('\n');
+    }
+    // Written next to the history and renamed over it, so a crash never leaves half a file
+    let temp_path = cache_path.with_extension("json.tmp");
+    fs::write(&temp_path, compacted)
+        .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+    fs::rename(&temp_path, cache_path)
+        .with_context(|| format!("Failed to replace {}", cache_path.display()))?;
+
+    info!("Cache compacted: {before} → {} entries", records.len());
+    Ok(())
+}
+
+fn slope(values: &[f32]) -> Option<f32> {
+    if values.len() < 2 {
+        return None;
+    }
+
+    let n = values.len() as f32;
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = values.iter().sum::<f32>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for (x, y) in values.iter().enumerate() {
+        let dx = x as f32 - mean_x;
+        covariance += dx * (y - mean_y);
+        variance += dx * dx;
+    }
+
+    Some(covariance / variance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hypixel::HypixelPlayerBuilder;
+
+    #[test]
+    fn slope_of_fkdr_points() {
+        assert_eq!(slope(&[1.0, 2.0, 3.0]), Some(1.0));
+        assert_eq!(slope(&[4.0, 3.0, 2.0, 1.0]), Some(-1.0));
+        assert_eq!(slope(&[2.0, 2.0]), Some(0.0));
+        assert_eq!(slope(&[2.0]), None);
+    }
+
+    #[test]
+    fn compaction_drops_duplicates_and_old_records() {
+        let path = std::env::temp_dir().join(format!(
+            "bwoverlay_history_{}.json",
+            uuid::Uuid::new_v4().simple()
+        ));
+        let now = now_secs();
+        let record = |uuid: &str, age: i64| {
+            let record = StatRecord {
+                uuid: uuid.to_string(),
+                timestamp: now - age,
+                fkdr: Some(1.0),
+                wlr: Some(1.0),
+                level: 100,
+                finals: 10,
+                wins: 10,
+            };
+            serde_json::to_string(&record).unwrap() + "\n"
+        };
+
+        let mut contents = record("steve", 60).repeat(3);
+        contents += &record("steve", HISTORY_MAX_AGE_SECS + 60);
+        contents += "not json\n";
+        for age in 0..12 {
+            contents += &record("alex", age);
+        }
+        fs::write(&path, contents).unwrap();
+
+        compact_cache(&path, 100).unwrap();
+        let history = StatHistory::load(path.clone()).unwrap();
+        assert_eq!(history.records["steve"].len(), 1);
+        assert_eq!(history.records["alex"].len(), RECORDS_PER_PLAYER);
+        // The oldest of alex's records went first, the survivors stay in lookup order
+        assert_eq!(history.records["alex"][0].timestamp, now - 9);
+
+        compact_cache(&path, 4).unwrap();
+        let history = StatHistory::load(path.clone()).unwrap();
+        assert!(!history.records.contains_key("steve"));
+        assert_eq!(history.records["alex"].len(), 4);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn undefined_ratios_survive_a_reload() {
+        let path = std::env::temp_dir().join(format!(
+            "bwoverlay_history_{}.json",
+            uuid::Uuid::new_v4().simple()
+        ));
+        let mut history = StatHistory::load(path.clone()).unwrap();
+        let player = |fkdr| HypixelPlayer {
+            wlr: -1.0,
+            ..HypixelPlayerBuilder::new("Steve", "steve")
+                .fkdr(fkdr)
+                .build()
+        };
+        // No final deaths yet
+        history.record(&player(f32::INFINITY)).unwrap();
+        history.record(&player(2.0)).unwrap();
+        history.record(&player(3.0)).unwrap();
+
+        let history = StatHistory::load(path.clone()).unwrap();
+        let records = &history.records["steve"];
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].fkdr, None);
+        assert_eq!(records[0].wlr, None);
+        assert_eq!(history.fkdr_trend(&"steve".to_string(), 3), Some(1.0));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use crate::{hypixel::HypixelPlayer, i18n::text};
+
+/// Every member above this FKDR makes a team more dangerous than its average suggests.
+const COORDINATED_FKDR: f32 = 3.0;
+/// Share of the average threat added for a team where every member is above `COORDINATED_FKDR`.
+const COORDINATED_BONUS: f32 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TeamColor {
+    Red,
+    Blue,
+    Green,
+    Yellow,
+    Aqua,
+    White,
+    Pink,
+    Gray,
+}
+
+impl TeamColor {
+    pub const ALL: [TeamColor; 8] = [
+        TeamColor::Red,
+        TeamColor::Blue,
+        TeamColor::Green,
+        TeamColor::Yellow,
+        TeamColor::Aqua,
+        TeamColor::White,
+        TeamColor::Pink,
+        TeamColor::Gray,
+    ];
+
+    /// Parses the team name Hypixel uses in chat, e.g. `Red` in `Red Bed was destroyed`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        TeamColor::ALL
+            .into_iter()
+            .find(|color| format!("{color:?}").eq_ignore_ascii_case(name))
+    }
+
+    /// The translated name shown in team headers.
+    pub fn label(self) -> &'static str {
+        text(match self {
+            TeamColor::Red => "color.red",
+            TeamColor::Blue => "color.blue",
+            TeamColor::Green => "color.green",
+            TeamColor::Yellow => "color.yellow",
+            TeamColor::Aqua => "color.aqua",
+            TeamColor::White => "color.white",
+            TeamColor::Pink => "color.pink",
+            TeamColor::Gray => "color.gray",
+        })
+    }
+}
+
+/// The players sharing one bed, assessed together since they play as a unit.
+#[derive(Debug, Clone)]
+pub struct PlayerGroup {
+    pub color: TeamColor,
+    pub members: Vec<HypixelPlayer>,
+}
+
+impl PlayerGroup {
+    /// Members with an unknown FKDR are left out, 0.0 when none is known.
+    pub fn average_fkdr(&self) -> f32 {
+        average(
+            self.members
+                .iter()
+                .map(|p| p.fkdr)
+                .filter(|&fkdr| fkdr >= 0.0),
+        )
+    }
+
+    /// Lowest known star level, -1 when none is known.
+    pub fn min_level(&self) -> i32 {
+        self.known_levels().min().unwrap_or(-1)
+    }
+
+    /// Highest known star level, -1 when none is known.
+    pub fn max_level(&self) -> i32 {
+        self.known_levels().max().unwrap_or(-1)
+    }
+
+    /// The average threat index of the members, raised when every one of them is strong.
+    pub fn group_threat_score(&self) -> f32 {
+        let average = average(
+            self.members
+                .iter()
+                .map(|p| p.index_in(None))
+                .filter(|&index| index >= 0.0),
+        );
+        let coordinated =
+            !self.members.is_empty() && self.members.iter().all(|p| p.fkdr > COORDINATED_FKDR);
+
+        if coordinated {
+            average * (1.0 + COORDINATED_BONUS)
+        } else {
+            average
+        }
+    }
+
+    fn known_levels(&self) -> impl Iterator<Item = i32> + '_ {
+        self.members
+            .iter()
+            .map(|p| p.level)
+            .filter(|&level| level >= 0)
+    }
+}
+
+/// Splits `players` into one group per team in `teams`, keyed by lowercased name, in
+/// `TeamColor::ALL` order. Players whose team isn't known are returned separately.
+pub fn group_by_team(
+    players: &[HypixelPlayer],
+    teams: &HashMap<String, TeamColor>,
+) -> (Vec<PlayerGroup>, Vec<HypixelPlayer>) {
+    let mut groups: Vec<PlayerGroup> = Vec::new();
+    let mut unassigned = Vec::new();
+    for player in players {
+        let Some(&color) = teams.get(&player.name.to_lowercase()) else {
+            unassigned.push(player.clone());
+            continue;
+        };
+        match groups.iter_mut().find(|group| group.color == color) {
+            Some(group) => group.members.push(player.clone()),
+            None => groups.push(PlayerGroup {
+                color,
+                members: vec![player.clone()],
+            }),
+        }
+    }
+    groups.sort_by_key(|group| {
+        TeamColor::ALL
+            .iter()
+            .position(|&color| color == group.color)
+    });
+    (groups, unassigned)
+}
+
+fn average(values: impl Iterator<Item = f32>) -> f32 {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hypixel::HypixelPlayerBuilder;
+
+    fn member(name: &str, level: i32, fkdr: f32) -> HypixelPlayer {
+        HypixelPlayerBuilder::new(name, "uuid")
+            .level(level)
+            .fkdr(fkdr)
+            .build()
+    }
+
+    #[test]
+    fn team_stats() {
+        let team = PlayerGroup {
+            color: TeamColor::Red,
+            members: vec![member("A", 100, 2.0), member("B", 300, 4.0)],
+        };
+
+        assert_eq!(team.average_fkdr(), 3.0);
+        assert_eq!(team.min_level(), 100);
+        assert_eq!(team.max_level(), 300);
+        // (100 * 2² + 300 * 4²) / 2, no bonus since A is below 3 FKDR
+        assert_eq!(team.group_threat_score(), 2_600.0);
+    }
+
+    #[test]
+    fn all_strong_members_raise_the_threat() {
+        let team = PlayerGroup {
+            color: TeamColor::Blue,
+            members: vec![member("A", 100, 4.0), member("B", 100, 4.0)],
+        };
+
+        assert_eq!(team.group_threat_score(), 1_600.0 * 1.25);
+        assert_eq!(TeamColor::from_name("blue"), Some(TeamColor::Blue));
+    }
+
+    #[test]
+    fn players_are_grouped_by_known_team() {
+        let players = [
+            member("Steve", 100, 2.0),
+            member("Alex", 100, 2.0),
+            member("Notch", 100, 2.0),
+        ];
+        let teams = HashMap::from([
+            ("steve".to_string(), TeamColor::Blue),
+            ("alex".to_string(), TeamColor::Red),
+        ]);
+
+        let (groups, unassigned) = group_by_team(&players, &teams);
+
+        let colors: Vec<_> = groups.iter().map(|group| group.color).collect();
+        assert_eq!(colors, [TeamColor::Red, TeamColor::Blue]);
+        assert_eq!(unassigned.len(), 1);
+        assert_eq!(unassigned[0].name, "Notch");
+    }
+}
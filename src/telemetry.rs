@@ -0,0 +1,83 @@
+use crate::{
+    api::{HypixelApiError, PlayerLookupError},
+    hypixel::ParseError,
+};
+use log::debug;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+/// Posts an anonymized report of `e` to `endpoint` in the background. Failures to deliver the
+/// report are only logged at debug level so telemetry can never get in the way of the overlay.
+pub fn report(endpoint: &str, e: &anyhow::Error) -> JoinHandle<()> {
+    let endpoint = endpoint.to_string();
+    let payload = sanitize_error(e);
+
+    tokio::spawn(async move {
+        if let Err(e) = Client::new().post(&endpoint).json(&payload).send().await {
+            debug!("Failed to send telemetry report: {}", e.without_url());
+        }
+    })
+}
+
+/// Reduces `e` to the kind of the innermost error the overlay recognises. Error messages are
+/// never included since they can carry player names, UUIDs or the API key.
+pub fn sanitize_error(e: &anyhow::Error) -> Value {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    json!({
+        "error_type": error_type(e),
+        "crate_version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "timestamp": timestamp,
+    })
+}
+
+fn error_type(e: &anyhow::Error) -> &'static str {
+    for cause in e.chain() {
+        // A failed request is classified by the reqwest error behind it, further down the chain
+        if let Some(HypixelApiError::KeyRejected(_)) = cause.downcast_ref::<HypixelApiError>() {
+            return "hypixel_key_rejected";
+        }
+        if let Some(e) = cause.downcast_ref::<PlayerLookupError>() {
+            return match e {
+                PlayerLookupError::MojangApiError { .. } => "mojang_status",
+                PlayerLookupError::HypixelApiError { .. } => "hypixel_status",
+                PlayerLookupError::ParseError { .. } => "json_parse",
+                PlayerLookupError::UuidParseError { .. } => "uuid_parse",
+                PlayerLookupError::RateLimited { .. } => "hypixel_rate_limited",
+                PlayerLookupError::PlayerNotFound { .. } => "hypixel_player_not_found",
+            };
+        }
+        if let Some(e) = cause.downcast_ref::<ParseError>() {
+            return match e {
+                ParseError::JsonParse { .. } => "json_parse",
+                ParseError::MissingField(_) => "missing_field",
+            };
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            return if e.is_timeout() {
+                "http_timeout"
+            } else if e.is_connect() {
+                "http_connect"
+            } else if e.is_status() {
+                "http_status"
+            } else if e.is_decode() {
+                "http_decode"
+            } else {
+                "http"
+            };
+        }
+        if cause.downcast_ref::<std::io::Error>().is_some() {
+            return "io";
+        }
+        if cause.downcast_ref::<toml::de::Error>().is_some() {
+            return "config_parse";
+        }
+    }
+    "other"
+}
@@ -0,0 +1,194 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::{
+    fs::OpenOptions,
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A `latest.log` in the temp directory that a test writes to the way Minecraft does, removed
+/// again when dropped.
+pub struct ReplayLogFile {
+    path: PathBuf,
+}
+
+impl ReplayLogFile {
+    pub fn new() -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "bwoverlay-replay-{}-{}.log",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&path, "").unwrap();
+
+        ReplayLogFile { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub async fn append_line(&self, line: &str) {
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .await
+            .unwrap();
+        file.write_all(format!("{line}\n").as_bytes())
+            .await
+            .unwrap();
+        file.flush().await.unwrap();
+    }
+
+    /// Empties the file like Minecraft starting a fresh log on launch.
+    pub async fn truncate(&self) {
+        OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(&self.path)
+            .await
+            .unwrap();
+    }
+}
+
+impl Drop for ReplayLogFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Canned responses for one mock Hypixel API, keyed by endpoint and dashless UUID.
+#[derive(Default)]
+struct MockRoutes {
+    responses: HashMap<(String, String), String>,
+    rate_limit_unknown: bool,
+    invalid_key: bool,
+    requests: usize,
+    /// How long every answer is held back.
+    delay: Duration,
+}
+
+/// A local stand-in for the Hypixel API. Point a client at it with
+/// `HypixelApiClient::with_base_url(server.base_url())`. UUIDs nothing was registered for get
+/// Hypixel's `"player": null` answer, or a 429 after `register_rate_limit`.
+pub struct MockHypixelServer {
+    addr: SocketAddr,
+    routes: Arc<Mutex<MockRoutes>>,
+}
+
+impl MockHypixelServer {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let routes = Arc::new(Mutex::new(MockRoutes::default()));
+
+        let server_routes = Arc::clone(&routes);
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(respond(socket, Arc::clone(&server_routes)));
+            }
+        });
+
+        MockHypixelServer { addr, routes }
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Serves `tests/fixtures/<fixture>.json` for `GET /player?uuid=<uuid>`.
+    pub fn register_player(&self, uuid: &str, fixture: &str) {
+        let path = format!(
+            "{}/tests/fixtures/{fixture}.json",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        self.register("player", uuid, &std::fs::read_to_string(path).unwrap());
+    }
+
+    pub fn register(&self, endpoint: &str, uuid: &str, body: &str) {
+        self.routes.lock().unwrap().responses.insert(
+            (endpoint.to_string(), uuid.replace('-', "")),
+            body.to_string(),
+        );
+    }
+
+    /// Answers 429 for every UUID nothing was registered for.
+    pub fn register_rate_limit(&self) {
+        self.routes.lock().unwrap().rate_limit_unknown = true;
+    }
+
+    /// Answers 403 for every request, like Hypixel does for a key it doesn't know.
+    pub fn register_invalid_key(&self) {
+        self.routes.lock().unwrap().invalid_key = true;
+    }
+
+    /// Holds every answer back for `delay`, like a slow or overloaded API.
+    pub fn set_delay(&self, delay: Duration) {
+        self.routes.lock().unwrap().delay = delay;
+    }
+
+    /// How many requests the server has answered.
+    pub fn request_count(&self) -> usize {
+        self.routes.lock().unwrap().requests
+    }
+}
+
+async fn respond(mut socket: TcpStream, routes: Arc<Mutex<MockRoutes>>) {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    while !request.ends_with(b"\r\n\r\n") {
+        match socket.read(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => request.extend_from_slice(&buf[..n]),
+        }
+    }
+
+    // e.g. `GET /player?key=...&uuid=... HTTP/1.1`
+    let request = String::from_utf8_lossy(&request);
+    let target = request.split_whitespace().nth(1).unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let uuid = query
+        .split('&')
+        .find_map(|param| param.strip_prefix("uuid="))
+        .unwrap_or_default()
+        .replace('-', "");
+
+    let (status, body, delay) = {
+        let mut routes = routes.lock().unwrap();
+        routes.requests += 1;
+        let (status, body) = match routes
+            .responses
+            .get(&(path.trim_start_matches('/').to_string(), uuid))
+        {
+            _ if routes.invalid_key => (
+                "403 Forbidden",
+                r#"{"success":false,"cause":"Invalid API key"}"#.to_string(),
+            ),
+            Some(body) => ("200 OK", body.clone()),
+            None if routes.rate_limit_unknown => (
+                "429 Too Many Requests",
+                r#"{"success":false,"cause":"Key throttle"}"#.to_string(),
+            ),
+            None => ("200 OK", r#"{"success":true,"player":null}"#.to_string()),
+        };
+        (status, body, routes.delay)
+    };
+    tokio::time::sleep(delay).await;
+
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         RateLimit-Remaining: 299\r\nRateLimit-Reset: 60\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = socket.write_all(response.as_bytes()).await;
+}
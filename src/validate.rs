@@ -0,0 +1,53 @@
+use std::{fs::File, sync::Arc};
+
+use crate::{
+    api::{ConnectionPool, HypixelApiClient},
+    config::{validate_config, Config},
+};
+
+fn report(name: &str, result: Result<(), String>) -> bool {
+    match result {
+        Ok(()) => {
+            println!("[PASS] {name}");
+            true
+        }
+        Err(reason) => {
+            println!("[FAIL] {name}: {reason}");
+            false
+        }
+    }
+}
+
+/// Runs every setup check, printing a pass/fail line for each. Returns whether all passed.
+pub async fn run(config: &Config) -> bool {
+    let problems = validate_config(config);
+    let config_ok = report(
+        "Config values",
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("; "))
+        },
+    );
+
+    let log_ok = report(
+        "Log file is readable",
+        File::open(&config.log_path)
+            .map(|_| ())
+            .map_err(|e| format!("{}: {e}", config.log_path)),
+    );
+
+    let client = match ConnectionPool::new(config) {
+        Ok(pool) => HypixelApiClient::new(config.api_key.clone(), Arc::new(pool)),
+        Err(e) => {
+            report("Hypixel API key", Err(format!("{e:#}")));
+            return false;
+        }
+    };
+    let key_ok = report(
+        "Hypixel API key",
+        client.check_key().await.map_err(|e| e.to_string()),
+    );
+
+    config_ok && log_ok && key_ok
+}
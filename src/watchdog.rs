@@ -0,0 +1,75 @@
+use log::{info, warn};
+use reqwest::Client;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+use crate::api::HYPIXEL_API;
+
+/// A small endpoint that needs no key, so probing it costs no quota. The status page itself
+/// keeps answering while the API is down, so the API is asked directly.
+const PROBE_ENDPOINT: &str = "v2/resources/games";
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Probes the Hypixel API every `CHECK_INTERVAL` and records whether it answered in
+/// `hypixel_up`, so lookups can be skipped during outages instead of failing one by one.
+pub fn spawn(hypixel_up: Arc<AtomicBool>, client: Arc<Client>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let up = api_up(&client, HYPIXEL_API).await;
+            let was_up = hypixel_up.swap(up, Ordering::Relaxed);
+
+            match (was_up, up) {
+                (true, false) => warn!("Hypixel API appears down, pausing lookups"),
+                (false, true) => info!("Hypixel API is back up"),
+                _ => {}
+            }
+        }
+    })
+}
+
+/// Whether the API at `base_url` answers the probe successfully.
+async fn api_up(client: &Client, base_url: &str) -> bool {
+    match client
+        .get(format!("{base_url}/{PROBE_ENDPOINT}"))
+        .send()
+        .await
+    {
+        Ok(resp) => resp.status().is_success(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::MockHypixelServer;
+
+    #[tokio::test]
+    async fn probes_the_api_rather_than_the_status_page() {
+        let server = MockHypixelServer::start().await;
+        server.register("v2/resources/games", "", r#"{"success":true,"games":{}}"#);
+        assert!(api_up(&Client::new(), &server.base_url()).await);
+        assert_eq!(server.request_count(), 1);
+
+        // Any error status counts as down
+        server.register_invalid_key();
+        assert!(!api_up(&Client::new(), &server.base_url()).await);
+
+        // Nothing listens on this port once the listener is dropped
+        let closed = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        assert!(!api_up(&Client::new(), &format!("http://{closed}")).await);
+    }
+}
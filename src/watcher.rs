@@ -0,0 +1,121 @@
+use hotwatch::{Event, EventKind, Hotwatch};
+use log::{info, warn};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs::File,
+    hash::{Hash, Hasher},
+    io::{Read, Seek, SeekFrom},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+const WATCH_RETRIES: u32 = 3;
+const WATCH_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// How many trailing lines of the log are hashed when polling, and how far back to look for them.
+const FINGERPRINT_LINES: usize = 5;
+const FINGERPRINT_TAIL_BYTES: u64 = 4096;
+
+/// Reads whatever was appended to the log. Shared by the hotwatch and polling paths.
+pub type LogHandler = Arc<dyn Fn() + Send + Sync>;
+
+/// Watch registrations across every log, reported as watches are added or fail, to tell a
+/// limit reached after a few logs apart from a watcher that never worked.
+pub struct WatcherHealth {
+    pub watch_count: u32,
+    pub error_count: u32,
+}
+
+impl WatcherHealth {
+    pub fn new() -> Self {
+        WatcherHealth {
+            watch_count: 0,
+            error_count: 0,
+        }
+    }
+}
+
+/// (Re)registers the hotwatch watch on `log_path`, retrying while the watch limit is exhausted.
+/// Returns `false` once every retry failed, in which case the caller should fall back to polling.
+pub async fn soft_reset_log_watcher(
+    hotwatch: &mut Hotwatch,
+    log_path: &str,
+    handler: LogHandler,
+    health: &Arc<Mutex<WatcherHealth>>,
+) -> bool {
+    for attempt in 0..=WATCH_RETRIES {
+        if attempt > 0 {
+            info!("Retrying log watch ({attempt}/{WATCH_RETRIES})");
+            tokio::time::sleep(WATCH_RETRY_DELAY).await;
+        }
+
+        let handler = Arc::clone(&handler);
+        let result = hotwatch.watch(log_path, move |event: Event| {
+            if let EventKind::Modify(_) = event.kind {
+                handler();
+            }
+        });
+
+        match result {
+            Ok(()) => {
+                let mut health = health.lock().unwrap();
+                health.watch_count += 1;
+                info!(
+                    "Watching log path: {log_path} ({} watched)",
+                    health.watch_count
+                );
+                return true;
+            }
+            Err(e) => {
+                let mut health = health.lock().unwrap();
+                health.error_count += 1;
+                warn!(
+                    "Failed to watch log file {log_path}: {e} ({} logs watched, {} failed attempts)",
+                    health.watch_count, health.error_count
+                );
+            }
+        }
+    }
+
+    false
+}
+
+/// Degraded-mode watcher that checks the log every `interval` and runs `handler` when it changed.
+pub fn spawn_polling(log_path: String, interval: Duration, handler: LogHandler) -> JoinHandle<()> {
+    tokio::spawn(poll(log_path, interval, handler))
+}
+
+async fn poll(log_path: String, interval: Duration, handler: LogHandler) {
+    let mut interval = tokio::time::interval(interval);
+    let mut last_seen: Option<(u64, u64)> = None;
+
+    loop {
+        interval.tick().await;
+
+        let Some(seen) = fingerprint(&log_path) else {
+            continue;
+        };
+        if last_seen != Some(seen) {
+            last_seen = Some(seen);
+            handler();
+        }
+    }
+}
+
+/// The log's size and a hash of its last few lines. Network drives don't always update the
+/// modification time, so the content is compared instead.
+fn fingerprint(log_path: &str) -> Option<(u64, u64)> {
+    let mut file = File::open(log_path).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(FINGERPRINT_TAIL_BYTES)))
+        .ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+
+    let mut hasher = DefaultHasher::new();
+    for line in tail.split(|&b| b == b'\n').rev().take(FINGERPRINT_LINES) {
+        line.hash(&mut hasher);
+    }
+    Some((len, hasher.finish()))
+}